@@ -1,21 +1,123 @@
-use serde::Deserialize;
+use crate::env;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // Default worktree configuration values
 fn default_worktree_path() -> String {
     "../worktrees/$FORCE_FEATURE_SLUG".to_string()
 }
 
+fn default_branch() -> String {
+    "$FORCE_FEATURE_SLUG".to_string()
+}
+
 fn default_remove_on_down() -> bool {
     true
 }
 
+/// Base port used when config.toml doesn't set `[ports] base`
+pub const DEFAULT_PORT_BASE: u16 = 4000;
+
+/// Number of port offsets used when config.toml doesn't set `[ports] range`
+pub const DEFAULT_PORT_RANGE: u16 = 1000;
+
+fn default_port_base() -> u16 {
+    DEFAULT_PORT_BASE
+}
+
+fn default_port_range() -> u16 {
+    DEFAULT_PORT_RANGE
+}
+
+// Default readiness check timing
+fn default_ready_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_ready_interval() -> Duration {
+    Duration::from_millis(500)
+}
+
 /// Project-level Force configuration from .force/config.toml
 #[derive(Debug, Deserialize, Default)]
 pub struct ForceConfig {
     #[serde(default)]
     pub worktree: WorktreeConfig,
+    #[serde(default)]
+    pub ports: PortsConfig,
+    /// Per-category options, keyed by category name
+    #[serde(default)]
+    pub categories: BTreeMap<String, CategoryConfig>,
+    /// Extra environment variables for every script. Values may reference
+    /// the built-in FORCE_* variables.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Files rendered into every worktree
+    #[serde(default)]
+    pub files: Vec<FileTemplate>,
+}
+
+impl ForceConfig {
+    /// Whether scripts in this category may run at the same time
+    pub fn is_parallel(&self, category: &str) -> bool {
+        self.categories.get(category).is_some_and(|c| c.parallel)
+    }
+}
+
+/// Options for a script category
+#[derive(Debug, Deserialize, Default)]
+pub struct CategoryConfig {
+    /// Run scripts in this category concurrently (default: false)
+    #[serde(default)]
+    pub parallel: bool,
+}
+
+/// Port allocation options
+#[derive(Debug, Deserialize)]
+pub struct PortsConfig {
+    /// Port exported as FORCE_PORT when the session offset is 0
+    #[serde(default = "default_port_base")]
+    pub base: u16,
+    /// Number of offsets sessions can be spread across (0..range)
+    #[serde(default = "default_port_range")]
+    pub range: u16,
+    /// Named ports, each exported as FORCE_PORT_<NAME> = base + session offset
+    #[serde(flatten)]
+    pub named: BTreeMap<String, u16>,
+}
+
+impl Default for PortsConfig {
+    fn default() -> Self {
+        Self {
+            base: default_port_base(),
+            range: default_port_range(),
+            named: BTreeMap::new(),
+        }
+    }
+}
+
+impl PortsConfig {
+    /// FORCE_PORT and the named ports for a session at `offset`. An offset
+    /// recorded under a larger range or lower base could push them past 65535.
+    pub fn session_ports(&self, offset: u16) -> Result<(u16, Vec<(String, u16)>), String> {
+        let port = |name: &str, base: u16| {
+            base.checked_add(offset).ok_or_else(|| {
+                format!(
+                    "Port offset {} puts '{}' ({}) past 65535; lower [ports] {} in config.toml",
+                    offset, name, base, name
+                )
+            })
+        };
+        let named = self
+            .named
+            .iter()
+            .map(|(name, base)| Ok((name.clone(), port(name, *base)?)))
+            .collect::<Result<_, String>>()?;
+        Ok((port("base", self.base)?, named))
+    }
 }
 
 /// Worktree configuration options
@@ -25,6 +127,37 @@ pub struct WorktreeConfig {
     pub path: String,
     #[serde(default = "default_remove_on_down")]
     pub remove_on_down: bool,
+    /// Branch name template for new worktrees
+    #[serde(default = "default_branch")]
+    pub branch: String,
+    /// Ref new branches start from (default: the main checkout's HEAD)
+    pub base: Option<String>,
+    /// Whether `force down` deletes the worktree's branch
+    #[serde(default)]
+    pub delete_branch: DeleteBranch,
+    /// Glob patterns copied from the main checkout into new worktrees
+    #[serde(default)]
+    pub copy: Vec<String>,
+    /// Glob patterns symlinked from the main checkout into new worktrees
+    #[serde(default)]
+    pub link: Vec<String>,
+    /// Check out submodules in new worktrees
+    #[serde(default)]
+    pub submodules: bool,
+    /// Fetch Git LFS objects in new worktrees
+    #[serde(default)]
+    pub lfs: bool,
+}
+
+/// When `force down` deletes the worktree's branch
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteBranch {
+    #[default]
+    Never,
+    /// Only if it's fully merged into the base branch
+    Merged,
+    Always,
 }
 
 impl Default for WorktreeConfig {
@@ -32,10 +165,42 @@ impl Default for WorktreeConfig {
         Self {
             path: default_worktree_path(),
             remove_on_down: default_remove_on_down(),
+            branch: default_branch(),
+            base: None,
+            delete_branch: DeleteBranch::default(),
+            copy: Vec::new(),
+            link: Vec::new(),
+            submodules: false,
+            lfs: false,
         }
     }
 }
 
+/// Check that every feature gets its own branch and that copy/link patterns
+/// stay inside the checkout
+fn validate_worktree(worktree: &WorktreeConfig) -> Result<(), String> {
+    if !worktree.branch.contains("$FORCE_FEATURE_SLUG") {
+        return Err(format!(
+            "[worktree] branch '{}' must include $FORCE_FEATURE_SLUG",
+            worktree.branch
+        ));
+    }
+    for (key, patterns) in [("copy", &worktree.copy), ("link", &worktree.link)] {
+        for pattern in patterns {
+            if pattern.is_empty()
+                || pattern.starts_with('/')
+                || pattern.split('/').any(|c| c == "..")
+            {
+                return Err(format!(
+                    "[worktree] {} pattern '{}' must be a relative path inside the project",
+                    key, pattern
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Load configuration from .force/config.toml
 pub fn load_config(force_dir: &Path) -> Result<ForceConfig, Box<dyn std::error::Error>> {
     let config_path = force_dir.join("config.toml");
@@ -45,27 +210,339 @@ pub fn load_config(force_dir: &Path) -> Result<ForceConfig, Box<dyn std::error::
     let content = fs::read_to_string(&config_path)?;
     let config: ForceConfig =
         toml::from_str(&content).map_err(|e| format!("Failed to parse config.toml: {}", e))?;
+    validate_ports(&config.ports)?;
+    validate_worktree(&config.worktree)?;
+    validate_env(
+        &config.env,
+        &env::builtin_var_names(&config.ports),
+        "[env] in config.toml",
+    )?;
+    let mut known_vars = env::builtin_var_names(&config.ports);
+    known_vars.extend(config.env.keys().cloned());
+    validate_files(&config.files, force_dir, &known_vars, "config.toml")?;
     Ok(config)
 }
 
+/// Check [[files]] entries: one content source, a valid mode, and a path
+/// that stays inside the worktree
+fn validate_files(
+    files: &[FileTemplate],
+    force_dir: &Path,
+    known: &[String],
+    context: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let placeholders: Vec<(String, String)> = known
+        .iter()
+        .map(|name| (name.clone(), String::new()))
+        .collect();
+    let check_references = |text: &str| {
+        env::expand(text, &placeholders).map_err(|e| format!("{}: [[files]] {}", context, e))
+    };
+
+    for file in files {
+        let sources = [
+            file.content.is_some(),
+            file.template.is_some(),
+            file.env.is_some(),
+        ];
+        if sources.iter().filter(|&&set| set).count() != 1 {
+            return Err(format!(
+                "{}: [[files]] '{}' needs exactly one of `content`, `template` or `env`",
+                context, file.path
+            )
+            .into());
+        }
+
+        let path = Path::new(&file.path);
+        let escapes = path.components().any(|c| {
+            matches!(
+                c,
+                std::path::Component::ParentDir
+                    | std::path::Component::RootDir
+                    | std::path::Component::Prefix(_)
+            )
+        });
+        if file.path.is_empty() || escapes {
+            return Err(format!(
+                "{}: [[files]] path '{}' must be relative to the worktree",
+                context, file.path
+            )
+            .into());
+        }
+
+        file.mode()
+            .map_err(|e| format!("{}: [[files]] '{}': {}", context, file.path, e))?;
+
+        check_references(&file.path)?;
+        if let Some(content) = &file.content {
+            check_references(content)?;
+        }
+        if let Some(template) = &file.template {
+            let content = fs::read_to_string(force_dir.join(template)).map_err(|e| {
+                format!(
+                    "{}: [[files]] template '{}' in .force/: {}",
+                    context, template, e
+                )
+            })?;
+            check_references(&content)?;
+        }
+        for value in file.env.iter().flat_map(|env| env.values()) {
+            check_references(value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check variable names and that every `$VAR` reference in the values is
+/// one of `known`
+fn validate_env(
+    vars: &BTreeMap<String, String>,
+    known: &[String],
+    context: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let placeholders: Vec<(String, String)> = known
+        .iter()
+        .map(|name| (name.clone(), String::new()))
+        .collect();
+
+    for (name, value) in vars {
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("{}: invalid variable name '{}'", context, name).into());
+        }
+        if name.starts_with("FORCE_") {
+            return Err(format!(
+                "{}: '{}' is reserved for Force's built-in variables",
+                context, name
+            )
+            .into());
+        }
+        env::expand(value, &placeholders).map_err(|e| format!("{}: {}", context, e))?;
+    }
+
+    Ok(())
+}
+
+fn validate_ports(ports: &PortsConfig) -> Result<(), Box<dyn std::error::Error>> {
+    if ports.range == 0 {
+        return Err("[ports] range in config.toml must be at least 1".into());
+    }
+    if ports.base as u32 + ports.range as u32 > u16::MAX as u32 {
+        return Err(format!(
+            "[ports] base + range in config.toml must stay under 65535 (got {} + {})",
+            ports.base, ports.range
+        )
+        .into());
+    }
+    for (name, &base) in &ports.named {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!(
+                "Invalid port name '{}' in config.toml: use letters, digits and underscores",
+                name
+            )
+            .into());
+        }
+        if ["offset", "base", "range"]
+            .iter()
+            .any(|reserved| name.eq_ignore_ascii_case(reserved))
+        {
+            return Err(format!("Port name '{}' in config.toml is reserved", name).into());
+        }
+        if base as u32 + ports.range as u32 > u16::MAX as u32 {
+            return Err(format!(
+                "Port '{}' in config.toml: {} + range {} must stay under 65535",
+                name, base, ports.range
+            )
+            .into());
+        }
+    }
+
+    // Each port hands out base..base + range, so one session's port must never
+    // land in another session's block. Ports sharing a base are the same block.
+    let blocks: Vec<(&str, u16)> = std::iter::once(("base", ports.base))
+        .chain(
+            ports
+                .named
+                .iter()
+                .map(|(name, &base)| (name.as_str(), base)),
+        )
+        .collect();
+    for (i, &(name, base)) in blocks.iter().enumerate() {
+        for &(other, other_base) in &blocks[..i] {
+            if base != other_base && base.abs_diff(other_base) < ports.range {
+                return Err(format!(
+                    "Port '{}' ({}) in config.toml overlaps '{}' ({}): with range {}, \
+                     ports must be equal or at least {} apart",
+                    name, base, other, other_base, ports.range, ports.range
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Parsed TOML script file
 #[derive(Debug, Deserialize)]
 pub struct Script {
     pub meta: ScriptMeta,
     pub up: ScriptCommand,
     pub down: Option<ScriptCommand>,
+    /// Run by `force rename` in the moved worktree
+    pub rename: Option<ScriptCommand>,
+    /// Files rendered into the worktree before scripts run
+    #[serde(default)]
+    pub files: Vec<FileTemplate>,
+}
+
+/// A file rendered into the worktree by `force up` and removed by
+/// `force down`. Exactly one of `content`, `template` or `env` must be set.
+#[derive(Debug, Deserialize)]
+pub struct FileTemplate {
+    /// Target path, relative to the worktree
+    pub path: String,
+    /// Inline template content
+    pub content: Option<String>,
+    /// Template file, relative to .force/
+    pub template: Option<String>,
+    /// Variables written as a dotenv file
+    pub env: Option<BTreeMap<String, String>>,
+    /// Octal file mode, e.g. "600"
+    pub mode: Option<String>,
+}
+
+impl FileTemplate {
+    /// Parsed file mode, if one is set
+    pub fn mode(&self) -> Result<Option<u32>, String> {
+        self.mode
+            .as_deref()
+            .map(|mode| {
+                u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+                    .ok()
+                    .filter(|&mode| mode <= 0o7777)
+                    .ok_or_else(|| format!("invalid file mode '{}'", mode))
+            })
+            .transpose()
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ScriptMeta {
     pub category: String,
     pub priority: Option<i32>,
+    /// Names of scripts (file names without .toml) that must run first
+    #[serde(default)]
+    pub depends_on: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ScriptCommand {
     pub run: String,
     pub description: Option<String>,
+    /// Run as a long-lived background process instead of waiting for it
+    #[serde(default)]
+    pub service: bool,
+    /// Check that must pass before later scripts start
+    pub ready: Option<ReadyCheck>,
+    /// Kill the command if it is still running after this long
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub timeout: Option<Duration>,
+    /// Extra attempts after the command fails or times out
+    #[serde(default)]
+    pub retries: u32,
+    /// Time to wait before each retry (default: none)
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub retry_delay: Duration,
+    /// Extra environment variables for this command, overriding [env] in
+    /// config.toml
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Readiness check for an up script. Exactly one of `tcp`, `http` or
+/// `command` must be set.
+#[derive(Debug, Deserialize)]
+pub struct ReadyCheck {
+    /// Port or host:port that must accept TCP connections
+    pub tcp: Option<String>,
+    /// Local http:// URL that must respond with a 2xx status
+    pub http: Option<String>,
+    /// Shell command that must exit 0
+    pub command: Option<String>,
+    /// Give up after this long (default: 30s)
+    #[serde(
+        default = "default_ready_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub timeout: Duration,
+    /// Time between attempts (default: 500ms)
+    #[serde(
+        default = "default_ready_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+}
+
+/// Parse a duration such as "500ms", "30s", "5m" or "1h"
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}'", value))?;
+
+    let secs = |per_unit: u64| {
+        number
+            .checked_mul(per_unit)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("Duration '{}' is too large", value))
+    };
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => Ok(Duration::from_secs(number)),
+        "m" => secs(60),
+        "h" => secs(60 * 60),
+        _ => Err(format!(
+            "Invalid duration '{}': use a number followed by ms, s, m or h",
+            value
+        )),
+    }
+}
+
+/// Format a duration in the largest unit that represents it exactly
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis == 0 || !millis.is_multiple_of(1000) {
+        return format!("{}ms", millis);
+    }
+    let secs = duration.as_secs();
+    if secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_duration(&value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_duration(deserializer).map(Some)
 }
 
 /// A loaded script with its file info
@@ -94,7 +571,17 @@ pub fn find_force_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
 }
 
 /// Load all TOML scripts from .force/ directory
-pub fn load_scripts(force_dir: &Path) -> Result<Vec<LoadedScript>, Box<dyn std::error::Error>> {
+pub fn load_scripts(
+    force_dir: &Path,
+    force_config: &ForceConfig,
+) -> Result<Vec<LoadedScript>, Box<dyn std::error::Error>> {
+    // Script [env] tables may reference built-ins and config.toml's [env]
+    let mut known_vars = env::builtin_var_names(&force_config.ports);
+    known_vars.extend(force_config.env.keys().cloned());
+    // [rename.env] may also reference the old session's values
+    let mut rename_vars = known_vars.clone();
+    rename_vars.extend(env::old_var_names(&force_config.ports));
+
     let mut scripts = Vec::new();
 
     for entry in fs::read_dir(force_dir)? {
@@ -117,6 +604,32 @@ pub fn load_scripts(force_dir: &Path) -> Result<Vec<LoadedScript>, Box<dyn std::
             let content = fs::read_to_string(&path)?;
             let script: Script = toml::from_str(&content)
                 .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+            validate_script(&path, &script)?;
+            validate_files(
+                &script.files,
+                force_dir,
+                &known_vars,
+                &path.display().to_string(),
+            )?;
+            validate_env(
+                &script.up.env,
+                &known_vars,
+                &format!("{} [up.env]", path.display()),
+            )?;
+            if let Some(down) = &script.down {
+                validate_env(
+                    &down.env,
+                    &known_vars,
+                    &format!("{} [down.env]", path.display()),
+                )?;
+            }
+            if let Some(rename) = &script.rename {
+                validate_env(
+                    &rename.env,
+                    &rename_vars,
+                    &format!("{} [rename.env]", path.display()),
+                )?;
+            }
 
             scripts.push(LoadedScript { name, script });
         }
@@ -139,7 +652,138 @@ pub fn load_scripts(force_dir: &Path) -> Result<Vec<LoadedScript>, Box<dyn std::
         a.name.cmp(&b.name)
     });
 
-    Ok(scripts)
+    sort_by_dependencies(scripts)
+}
+
+/// Check script options that the TOML schema alone can't express
+fn validate_script(path: &Path, script: &Script) -> Result<(), Box<dyn std::error::Error>> {
+    for command in script.down.iter().chain(&script.rename) {
+        if command.service {
+            return Err(format!("{}: `service` is only supported in [up]", path.display()).into());
+        }
+        if command.ready.is_some() {
+            return Err(format!("{}: `ready` is only supported in [up]", path.display()).into());
+        }
+    }
+
+    if script.up.service && (script.up.timeout.is_some() || script.up.retries > 0) {
+        return Err(format!(
+            "{}: `timeout` and `retries` don't apply to services",
+            path.display()
+        )
+        .into());
+    }
+
+    if let Some(ready) = &script.up.ready {
+        let checks = [&ready.tcp, &ready.http, &ready.command]
+            .iter()
+            .filter(|c| c.is_some())
+            .count();
+        if checks != 1 {
+            return Err(format!(
+                "{}: [up.ready] needs exactly one of `tcp`, `http` or `command`",
+                path.display()
+            )
+            .into());
+        }
+        if ready
+            .http
+            .as_ref()
+            .is_some_and(|url| !url.starts_with("http://"))
+        {
+            return Err(format!(
+                "{}: [up.ready] `http` must be an http:// URL",
+                path.display()
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reorder scripts so each one runs after everything in its `depends_on`.
+///
+/// Scripts keep their category/priority/filename order wherever dependencies
+/// allow it: at each step the earliest script whose dependencies have all run
+/// is picked next.
+fn sort_by_dependencies(
+    scripts: Vec<LoadedScript>,
+) -> Result<Vec<LoadedScript>, Box<dyn std::error::Error>> {
+    let index_of: HashMap<&str, usize> = scripts
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.name.as_str(), i))
+        .collect();
+
+    // deps[i] holds the indices script i depends on
+    let mut deps: Vec<Vec<usize>> = Vec::with_capacity(scripts.len());
+    for script in &scripts {
+        let mut script_deps = Vec::new();
+        for dep in &script.script.meta.depends_on {
+            match index_of.get(dep.as_str()) {
+                Some(&i) => script_deps.push(i),
+                None => {
+                    return Err(format!(
+                        "{}.toml depends on '{}', but {}.toml doesn't exist",
+                        script.name, dep, dep
+                    )
+                    .into());
+                }
+            }
+        }
+        deps.push(script_deps);
+    }
+
+    let mut done = vec![false; scripts.len()];
+    let mut order = Vec::with_capacity(scripts.len());
+    while order.len() < scripts.len() {
+        let next = (0..scripts.len()).find(|&i| !done[i] && deps[i].iter().all(|&d| done[d]));
+        match next {
+            Some(i) => {
+                done[i] = true;
+                order.push(i);
+            }
+            None => {
+                let cycle = find_cycle(&deps, &done)
+                    .iter()
+                    .map(|&i| format!("{}.toml", scripts[i].name))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(format!("Dependency cycle between scripts: {}", cycle).into());
+            }
+        }
+    }
+
+    let mut slots: Vec<Option<LoadedScript>> = scripts.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .map(|i| slots[i].take().expect("each script is ordered once"))
+        .collect())
+}
+
+/// Walk dependencies among unfinished scripts until one repeats, returning
+/// the cycle with its first script repeated at the end
+fn find_cycle(deps: &[Vec<usize>], done: &[bool]) -> Vec<usize> {
+    let Some(start) = (0..deps.len()).find(|&i| !done[i]) else {
+        return Vec::new();
+    };
+
+    let mut path = vec![start];
+    let mut current = start;
+    loop {
+        // Every unfinished script has at least one unfinished dependency
+        let Some(&next) = deps[current].iter().find(|&&d| !done[d]) else {
+            return path;
+        };
+        if let Some(pos) = path.iter().position(|&i| i == next) {
+            let mut cycle = path.split_off(pos);
+            cycle.push(next);
+            return cycle;
+        }
+        path.push(next);
+        current = next;
+    }
 }
 
 #[cfg(test)]
@@ -160,6 +804,7 @@ run = "echo hello"
         assert_eq!(script.meta.priority, None);
         assert_eq!(script.up.run, "echo hello");
         assert_eq!(script.up.description, None);
+        assert!(!script.up.service);
         assert!(script.down.is_none());
     }
 
@@ -189,6 +834,206 @@ description = "Say goodbye"
         assert_eq!(down.description, Some("Say goodbye".to_string()));
     }
 
+    #[test]
+    fn test_parse_service_script() {
+        let toml = r#"
+[meta]
+category = "services"
+
+[up]
+run = "mix phx.server"
+service = true
+"#;
+        let script: Script = toml::from_str(toml).unwrap();
+        assert!(script.up.service);
+    }
+
+    #[test]
+    fn test_parse_ready_check() {
+        let toml = r#"
+[meta]
+category = "services"
+
+[up]
+run = "mix phx.server"
+service = true
+
+[up.ready]
+tcp = "$FORCE_PORT"
+timeout = "1m"
+"#;
+        let script: Script = toml::from_str(toml).unwrap();
+        let ready = script.up.ready.as_ref().unwrap();
+        assert_eq!(ready.tcp.as_deref(), Some("$FORCE_PORT"));
+        assert_eq!(ready.timeout, Duration::from_secs(60));
+        assert_eq!(ready.interval, Duration::from_millis(500));
+        assert!(validate_script(Path::new("server.toml"), &script).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_multiple_ready_checks() {
+        let toml = r#"
+[meta]
+category = "services"
+
+[up]
+run = "true"
+
+[up.ready]
+tcp = "4000"
+command = "true"
+"#;
+        let script: Script = toml::from_str(toml).unwrap();
+        let err = validate_script(Path::new("server.toml"), &script).unwrap_err();
+        assert!(err.to_string().contains("exactly one"));
+    }
+
+    #[test]
+    fn test_validate_rejects_https_ready_check() {
+        let toml = r#"
+[meta]
+category = "services"
+
+[up]
+run = "true"
+
+[up.ready]
+http = "https://localhost:4000"
+"#;
+        let script: Script = toml::from_str(toml).unwrap();
+        assert!(validate_script(Path::new("server.toml"), &script).is_err());
+    }
+
+    #[test]
+    fn test_parse_timeout_and_retries() {
+        let toml = r#"
+[meta]
+category = "deps"
+
+[up]
+run = "npm install"
+timeout = "5m"
+retries = 2
+retry_delay = "5s"
+
+[down]
+run = "dropdb app"
+timeout = "30s"
+"#;
+        let script: Script = toml::from_str(toml).unwrap();
+        assert_eq!(script.up.timeout, Some(Duration::from_secs(300)));
+        assert_eq!(script.up.retries, 2);
+        assert_eq!(script.up.retry_delay, Duration::from_secs(5));
+        let down = script.down.unwrap();
+        assert_eq!(down.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(down.retries, 0);
+        assert_eq!(down.retry_delay, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_validate_rejects_service_timeout() {
+        let toml = r#"
+[meta]
+category = "services"
+
+[up]
+run = "mix phx.server"
+service = true
+timeout = "1m"
+"#;
+        let script: Script = toml::from_str(toml).unwrap();
+        assert!(validate_script(Path::new("server.toml"), &script).is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1500ms");
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(300)), "5m");
+        assert_eq!(format_duration(Duration::from_secs(7200)), "2h");
+        assert_eq!(format_duration(Duration::ZERO), "0ms");
+    }
+
+    #[test]
+    fn test_validate_env() {
+        let known = vec!["FORCE_DB_NAME".to_string()];
+        let vars =
+            |name: &str, value: &str| BTreeMap::from([(name.to_string(), value.to_string())]);
+
+        let ok = vars("DATABASE_URL", "postgres://localhost/${FORCE_DB_NAME}");
+        assert!(validate_env(&ok, &known, "[env]").is_ok());
+
+        let unknown = vars("DATABASE_URL", "postgres://localhost/$FORCE_DB");
+        let err = validate_env(&unknown, &known, "[env]").unwrap_err();
+        assert!(err.to_string().contains("Unknown variable 'FORCE_DB'"));
+
+        assert!(validate_env(&vars("1PORT", "x"), &known, "[env]").is_err());
+        assert!(validate_env(&vars("FORCE_PORT", "x"), &known, "[env]").is_err());
+    }
+
+    #[test]
+    fn test_parse_file_templates() {
+        let toml = r#"
+[meta]
+category = "setup"
+
+[[files]]
+path = ".dev.local.env"
+mode = "600"
+
+[files.env]
+PORT = "$FORCE_PORT"
+
+[[files]]
+path = "config/dev.json"
+content = "{ \"port\": $FORCE_PORT }"
+
+[up]
+run = "true"
+"#;
+        let script: Script = toml::from_str(toml).unwrap();
+        assert_eq!(script.files.len(), 2);
+        assert_eq!(script.files[0].mode(), Ok(Some(0o600)));
+        assert!(script.files[0].env.is_some());
+        assert_eq!(script.files[1].mode(), Ok(None));
+        let known = vec!["FORCE_PORT".to_string()];
+        assert!(validate_files(&script.files, Path::new("."), &known, "env.toml").is_ok());
+        assert!(validate_files(&script.files, Path::new("."), &[], "env.toml").is_err());
+    }
+
+    #[test]
+    fn test_validate_files_rejects_bad_entries() {
+        let file = |path: &str, content: Option<&str>, mode: Option<&str>| FileTemplate {
+            path: path.to_string(),
+            content: content.map(String::from),
+            template: None,
+            env: None,
+            mode: mode.map(String::from),
+        };
+        let dir = Path::new(".");
+
+        assert!(validate_files(&[file(".env", None, None)], dir, &[], "x").is_err());
+        assert!(validate_files(&[file("../.env", Some("A=1"), None)], dir, &[], "x").is_err());
+        assert!(validate_files(&[file("/tmp/.env", Some("A=1"), None)], dir, &[], "x").is_err());
+        assert!(validate_files(&[file(".env", Some("A=1"), Some("9"))], dir, &[], "x").is_err());
+        assert!(validate_files(&[file(".env", Some("A=1"), Some("0o640"))], dir, &[], "x").is_ok());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5 days").is_err());
+        assert_eq!(
+            parse_duration("18446744073709551615h"),
+            Err("Duration '18446744073709551615h' is too large".to_string())
+        );
+    }
+
     #[test]
     fn test_parse_missing_category_fails() {
         let toml = r#"
@@ -238,6 +1083,89 @@ run = "echo hello"
         assert_eq!(script.meta.priority, Some(-10));
     }
 
+    fn loaded(name: &str, category: &str, depends_on: &[&str]) -> LoadedScript {
+        let deps = depends_on
+            .iter()
+            .map(|d| format!("\"{}\"", d))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let toml = format!(
+            "[meta]\ncategory = \"{}\"\ndepends_on = [{}]\n\n[up]\nrun = \"true\"\n",
+            category, deps
+        );
+        LoadedScript {
+            name: name.to_string(),
+            script: toml::from_str(&toml).unwrap(),
+        }
+    }
+
+    fn names(scripts: &[LoadedScript]) -> Vec<&str> {
+        scripts.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_depends_on() {
+        let toml = r#"
+[meta]
+category = "setup"
+depends_on = ["database"]
+
+[up]
+run = "echo hello"
+"#;
+        let script: Script = toml::from_str(toml).unwrap();
+        assert_eq!(script.meta.depends_on, vec!["database".to_string()]);
+    }
+
+    #[test]
+    fn test_sort_without_dependencies_keeps_order() {
+        let scripts = vec![loaded("a", "setup", &[]), loaded("b", "setup", &[])];
+        let sorted = sort_by_dependencies(scripts).unwrap();
+        assert_eq!(names(&sorted), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_sort_moves_script_after_dependency() {
+        let scripts = vec![
+            loaded("migrate", "db", &["database"]),
+            loaded("other", "db", &[]),
+            loaded("database", "setup", &[]),
+        ];
+        let sorted = sort_by_dependencies(scripts).unwrap();
+        assert_eq!(names(&sorted), vec!["other", "database", "migrate"]);
+    }
+
+    #[test]
+    fn test_sort_rejects_missing_dependency() {
+        let scripts = vec![loaded("migrate", "setup", &["databse"])];
+        let err = sort_by_dependencies(scripts).err().unwrap().to_string();
+        assert!(err.contains("migrate.toml"));
+        assert!(err.contains("databse"));
+    }
+
+    #[test]
+    fn test_sort_rejects_cycle() {
+        let scripts = vec![
+            loaded("a", "setup", &["c"]),
+            loaded("b", "setup", &["a"]),
+            loaded("c", "setup", &["b"]),
+            loaded("d", "setup", &[]),
+        ];
+        let err = sort_by_dependencies(scripts).err().unwrap().to_string();
+        assert!(err.contains("Dependency cycle"));
+        assert!(err.contains("a.toml"));
+        assert!(err.contains("b.toml"));
+        assert!(err.contains("c.toml"));
+        assert!(!err.contains("d.toml"));
+    }
+
+    #[test]
+    fn test_sort_rejects_self_dependency() {
+        let scripts = vec![loaded("a", "setup", &["a"])];
+        let err = sort_by_dependencies(scripts).err().unwrap().to_string();
+        assert!(err.contains("a.toml -> a.toml"));
+    }
+
     #[test]
     fn test_force_config_defaults() {
         let config = ForceConfig::default();
@@ -275,4 +1203,165 @@ remove_on_down = false
         assert_eq!(config.worktree.path, "/tmp/worktrees/$FORCE_FEATURE_SLUG");
         assert!(!config.worktree.remove_on_down);
     }
+
+    #[test]
+    fn test_parse_branch_and_base() {
+        let toml = r#"
+[worktree]
+branch = "agent/$FORCE_FEATURE_SLUG"
+base = "origin/main"
+"#;
+        let config: ForceConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.worktree.branch, "agent/$FORCE_FEATURE_SLUG");
+        assert_eq!(config.worktree.base.as_deref(), Some("origin/main"));
+        assert!(validate_worktree(&config.worktree).is_ok());
+
+        let defaults = WorktreeConfig::default();
+        assert_eq!(defaults.branch, "$FORCE_FEATURE_SLUG");
+        assert_eq!(defaults.base, None);
+        assert!(!defaults.submodules);
+        assert!(!defaults.lfs);
+    }
+
+    #[test]
+    fn test_parse_delete_branch() {
+        let config: ForceConfig =
+            toml::from_str("[worktree]\ndelete_branch = \"merged\"\n").unwrap();
+        assert_eq!(config.worktree.delete_branch, DeleteBranch::Merged);
+        assert_eq!(WorktreeConfig::default().delete_branch, DeleteBranch::Never);
+        assert!(toml::from_str::<ForceConfig>("[worktree]\ndelete_branch = \"yes\"\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_worktree_requires_slug_in_branch() {
+        let worktree = WorktreeConfig {
+            branch: "agent/work".to_string(),
+            ..WorktreeConfig::default()
+        };
+        let err = validate_worktree(&worktree).unwrap_err();
+        assert!(err.contains("$FORCE_FEATURE_SLUG"));
+    }
+
+    #[test]
+    fn test_validate_worktree_copy_patterns() {
+        let config: ForceConfig = toml::from_str(
+            "[worktree]\ncopy = [\".env\", \"config/*.key\"]\nlink = [\"node_modules\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.worktree.copy, vec![".env", "config/*.key"]);
+        assert!(validate_worktree(&config.worktree).is_ok());
+
+        for bad in ["../secrets", "/etc/hosts", ""] {
+            let worktree = WorktreeConfig {
+                link: vec![bad.to_string()],
+                ..WorktreeConfig::default()
+            };
+            assert!(validate_worktree(&worktree).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_parallel_categories() {
+        let toml = r#"
+[categories.deps]
+parallel = true
+
+[categories.setup]
+"#;
+        let config: ForceConfig = toml::from_str(toml).unwrap();
+        assert!(config.is_parallel("deps"));
+        assert!(!config.is_parallel("setup"));
+        assert!(!config.is_parallel("unknown"));
+    }
+
+    #[test]
+    fn test_parse_named_ports() {
+        let toml = r#"
+[ports]
+web = 4000
+api = 5000
+vite = 6000
+"#;
+        let config: ForceConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.ports.named.len(), 3);
+        assert_eq!(config.ports.named.get("api"), Some(&5000));
+        assert!(validate_ports(&config.ports).is_ok());
+    }
+
+    #[test]
+    fn test_default_config_has_no_named_ports() {
+        let config = ForceConfig::default();
+        assert!(config.ports.named.is_empty());
+        assert_eq!(config.ports.base, 4000);
+        assert_eq!(config.ports.range, 1000);
+    }
+
+    #[test]
+    fn test_parse_port_base_and_range() {
+        let toml = r#"
+[ports]
+base = 20000
+range = 5000
+web = 30000
+"#;
+        let config: ForceConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.ports.base, 20000);
+        assert_eq!(config.ports.range, 5000);
+        assert_eq!(config.ports.named.len(), 1);
+        assert_eq!(config.ports.named.get("web"), Some(&30000));
+        assert!(validate_ports(&config.ports).is_ok());
+    }
+
+    #[test]
+    fn test_validate_ports_rejects_base_range_overflow() {
+        let config: ForceConfig = toml::from_str("[ports]\nbase = 60000\nrange = 6000\n").unwrap();
+        assert!(validate_ports(&config.ports).is_err());
+    }
+
+    #[test]
+    fn test_validate_ports_rejects_empty_range() {
+        let config: ForceConfig = toml::from_str("[ports]\nrange = 0\n").unwrap();
+        assert!(validate_ports(&config.ports).is_err());
+    }
+
+    #[test]
+    fn test_validate_ports_rejects_invalid_name() {
+        let config: ForceConfig = toml::from_str("[ports]\n\"web-server\" = 4000\n").unwrap();
+        assert!(validate_ports(&config.ports).is_err());
+    }
+
+    #[test]
+    fn test_validate_ports_rejects_reserved_name() {
+        let config: ForceConfig = toml::from_str("[ports]\noffset = 4000\n").unwrap();
+        assert!(validate_ports(&config.ports).is_err());
+    }
+
+    #[test]
+    fn test_validate_ports_rejects_overflow() {
+        let config: ForceConfig = toml::from_str("[ports]\nweb = 65000\n").unwrap();
+        assert!(validate_ports(&config.ports).is_err());
+
+        let config: ForceConfig = toml::from_str("[ports]\nrange = 20000\nweb = 50000\n").unwrap();
+        assert!(validate_ports(&config.ports).is_err());
+    }
+
+    #[test]
+    fn test_validate_ports_rejects_overlap() {
+        let config: ForceConfig = toml::from_str("[ports]\napi = 4500\n").unwrap();
+        let err = validate_ports(&config.ports).unwrap_err().to_string();
+        assert!(err.contains("'api' (4500)"), "{}", err);
+        assert!(err.contains("'base' (4000)"), "{}", err);
+
+        let config: ForceConfig = toml::from_str("[ports]\napi = 5000\nweb = 5999\n").unwrap();
+        assert!(validate_ports(&config.ports).is_err());
+
+        // The spacing that works for the default range overlaps with a wider one
+        let config: ForceConfig =
+            toml::from_str("[ports]\nrange = 2000\nweb = 4000\napi = 5000\nvite = 6000\n").unwrap();
+        assert!(validate_ports(&config.ports).is_err());
+
+        // Sharing the base, or sitting exactly one range away, is fine
+        let config: ForceConfig = toml::from_str("[ports]\nweb = 4000\napi = 5000\n").unwrap();
+        assert!(validate_ports(&config.ports).is_ok());
+    }
 }
@@ -0,0 +1,336 @@
+use crate::config::WorktreeConfig;
+use crate::files;
+use crate::state::GeneratedFile;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Copy `[worktree] copy` matches and symlink `[worktree] link` matches from
+/// the main checkout into a worktree. Paths that already exist in the worktree
+/// are left alone. Returns each path created, with its hash; a copied
+/// directory is one record covering everything inside it.
+pub fn seed_worktree(
+    project_root: &Path,
+    worktree_path: &Path,
+    config: &WorktreeConfig,
+) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+    let mut seeded = Vec::new();
+    for relative in expand(project_root, &config.copy, worktree_path) {
+        let target = worktree_path.join(&relative);
+        if target.symlink_metadata().is_ok() {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        copy_path(&project_root.join(&relative), &target)
+            .map_err(|e| format!("Failed to copy {}: {}", relative.display(), e))?;
+        seeded.extend(record(&target));
+        println!("Copied {}", relative.display());
+    }
+
+    for relative in expand(project_root, &config.link, worktree_path) {
+        let target = worktree_path.join(&relative);
+        if target.symlink_metadata().is_ok() {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        symlink(project_root.join(&relative), &target)
+            .map_err(|e| format!("Failed to link {}: {}", relative.display(), e))?;
+        seeded.extend(record(&target));
+        println!("Linked {}", relative.display());
+    }
+
+    Ok(seeded)
+}
+
+fn record(path: &Path) -> Option<GeneratedFile> {
+    Some(GeneratedFile {
+        path: path.to_path_buf(),
+        hash: files::path_hash(path)?,
+    })
+}
+
+/// Expand glob patterns against `root`, returning matching paths relative to
+/// it. A matched directory stands for everything inside it. `.git` and
+/// nested repositories or worktrees (such as `skip` itself) are never entered.
+fn expand(root: &Path, patterns: &[String], skip: &Path) -> Vec<PathBuf> {
+    let mut matches = Vec::new();
+    for pattern in patterns {
+        let components: Vec<&str> = pattern
+            .split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .collect();
+        walk(root, PathBuf::new(), &components, skip, &mut matches);
+    }
+
+    matches.sort();
+    matches.dedup();
+    // Drop paths already covered by a matched parent directory
+    let mut covered: Vec<PathBuf> = Vec::new();
+    for path in matches {
+        if !covered.iter().any(|parent| path.starts_with(parent)) {
+            covered.push(path);
+        }
+    }
+    covered
+}
+
+fn walk(root: &Path, relative: PathBuf, pattern: &[&str], skip: &Path, out: &mut Vec<PathBuf>) {
+    let Some((first, rest)) = pattern.split_first() else {
+        if !relative.as_os_str().is_empty() {
+            out.push(relative);
+        }
+        return;
+    };
+
+    let dir = root.join(&relative);
+    if *first == "**" {
+        // Zero directories, then one more level with ** still in place
+        walk(root, relative.clone(), rest, skip, out);
+        for name in entries(&dir) {
+            let path = dir.join(&name);
+            let is_dir = path.symlink_metadata().is_ok_and(|m| m.is_dir());
+            if is_dir && !is_repository(&path, skip) {
+                walk(root, relative.join(&name), pattern, skip, out);
+            }
+        }
+        return;
+    }
+
+    for name in entries(&dir) {
+        if name == ".git" || !matches(first, &name) {
+            continue;
+        }
+        let path = dir.join(&name);
+        if !rest.is_empty() && (!path.is_dir() || is_repository(&path, skip)) {
+            continue;
+        }
+        walk(root, relative.join(&name), rest, skip, out);
+    }
+}
+
+fn entries(dir: &Path) -> Vec<String> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = read_dir
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+fn is_repository(path: &Path, skip: &Path) -> bool {
+    path.join(".git").symlink_metadata().is_ok() || path == skip
+}
+
+/// Match a single path component against a pattern with `*` and `?`.
+/// Wildcards don't match a leading dot, so `*` skips hidden files.
+fn matches(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last `*`: pattern index and name index
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // Let the last `*` swallow one more character
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Copy a file or directory tree, using copy-on-write clones where the
+/// filesystem supports them
+fn copy_path(source: &Path, target: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cp = Command::new("cp");
+    if cfg!(target_os = "macos") {
+        cp.arg("-Rpc");
+    } else {
+        cp.args(["-a", "--reflink=auto"]);
+    }
+    let cloned = cp
+        .arg(source)
+        .arg(target)
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if cloned {
+        return Ok(());
+    }
+
+    // cp is missing or can't clone here, so copy byte by byte
+    if target.is_dir() {
+        fs::remove_dir_all(target)?;
+    } else if target.symlink_metadata().is_ok() {
+        fs::remove_file(target)?;
+    }
+    copy_recursive(source, target)
+}
+
+fn copy_recursive(source: &Path, target: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = source.symlink_metadata()?;
+    if metadata.is_symlink() {
+        symlink(fs::read_link(source)?, target)?;
+    } else if metadata.is_dir() {
+        fs::create_dir(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        fs::set_permissions(target, metadata.permissions())?;
+    } else {
+        fs::copy(source, target)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("*.key", "master.key"));
+        assert!(matches("*", "node_modules"));
+        assert!(matches("cert?.pem", "cert1.pem"));
+        assert!(matches("a*b*c", "aXXbYYbc"));
+        assert!(matches(".env*", ".env.local"));
+        assert!(!matches("*.key", "master.key.bak"));
+        assert!(!matches("cert?.pem", "cert.pem"));
+        assert!(!matches("*", ".env"));
+    }
+
+    #[test]
+    fn test_expand() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for file in [
+            ".env",
+            "config/master.key",
+            "config/app.yml",
+            "certs/dev/local.key",
+            "node_modules/pkg/index.js",
+            ".worktrees/other/config/master.key",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::write(root.join(".worktrees/other/.git"), "gitdir: x").unwrap();
+
+        let patterns = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let skip = root.join(".worktrees/current");
+
+        assert_eq!(
+            expand(
+                root,
+                &patterns(&[".env", "node_modules", "node_modules/pkg"]),
+                &skip
+            ),
+            vec![PathBuf::from(".env"), PathBuf::from("node_modules")]
+        );
+        assert_eq!(
+            expand(root, &patterns(&["**/*.key"]), &skip),
+            vec![
+                PathBuf::from("certs/dev/local.key"),
+                PathBuf::from("config/master.key")
+            ]
+        );
+        assert!(expand(root, &patterns(&["missing/*"]), &skip).is_empty());
+    }
+
+    #[test]
+    fn test_seed_worktree_copies_and_links() {
+        let dir = TempDir::new().unwrap();
+        let main = dir.path().join("main");
+        let worktree = dir.path().join("worktree");
+        fs::create_dir_all(main.join("node_modules/pkg")).unwrap();
+        fs::write(main.join("node_modules/pkg/index.js"), "js").unwrap();
+        fs::write(main.join(".env"), "SECRET=1").unwrap();
+        fs::create_dir_all(&worktree).unwrap();
+
+        let config = WorktreeConfig {
+            copy: vec![".env".to_string()],
+            link: vec!["node_modules".to_string()],
+            ..WorktreeConfig::default()
+        };
+        let seeded = seed_worktree(&main, &worktree, &config).unwrap();
+        let paths: Vec<&Path> = seeded.iter().map(|f| f.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                worktree.join(".env").as_path(),
+                worktree.join("node_modules").as_path()
+            ]
+        );
+
+        assert_eq!(
+            fs::read_to_string(worktree.join(".env")).unwrap(),
+            "SECRET=1"
+        );
+        assert!(!worktree.join(".env").is_symlink());
+        assert_eq!(
+            fs::read_link(worktree.join("node_modules")).unwrap(),
+            main.join("node_modules")
+        );
+
+        // Existing files in the worktree are never overwritten
+        fs::write(worktree.join(".env"), "EDITED=1").unwrap();
+        fs::write(main.join("node_modules/pkg/index.js"), "edited").unwrap();
+        assert!(!files::is_unchanged(&seeded[0]));
+        assert!(files::is_unchanged(&seeded[1]));
+        assert!(seed_worktree(&main, &worktree, &config).unwrap().is_empty());
+        assert_eq!(
+            fs::read_to_string(worktree.join(".env")).unwrap(),
+            "EDITED=1"
+        );
+    }
+
+    #[test]
+    fn test_seed_worktree_records_copied_directory_once() {
+        let dir = TempDir::new().unwrap();
+        let main = dir.path().join("main");
+        let worktree = dir.path().join("worktree");
+        for file in ["a.js", "b.js", "nested/c.js"] {
+            let path = main.join("deps").join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+        fs::create_dir_all(&worktree).unwrap();
+
+        let config = WorktreeConfig {
+            copy: vec!["deps".to_string()],
+            ..WorktreeConfig::default()
+        };
+        let seeded = seed_worktree(&main, &worktree, &config).unwrap();
+        assert_eq!(seeded.len(), 1);
+        assert_eq!(seeded[0].path, worktree.join("deps"));
+        assert!(files::is_unchanged(&seeded[0]));
+
+        // Any change inside makes the whole directory the user's
+        fs::write(worktree.join("deps/nested/c.js"), "edited").unwrap();
+        assert!(!files::is_unchanged(&seeded[0]));
+    }
+}
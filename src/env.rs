@@ -1,47 +1,110 @@
+use crate::config::PortsConfig;
+use crate::state::Session;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-const BASE_PORT: u16 = 4000;
-
 /// Environment context for scripts
+#[derive(Clone)]
 pub struct ForceEnv {
     pub feature: String,
     pub feature_slug: String,
     pub port_offset: u16,
     pub port: u16,
+    /// Named ports from config.toml as (name, port)
+    pub ports: Vec<(String, u16)>,
     pub db_name: String,
     pub force_dir: PathBuf,
     pub worktree: PathBuf,
+    /// Custom variables from [env] tables as (name, expanded value)
+    pub vars: Vec<(String, String)>,
 }
 
 impl ForceEnv {
-    pub fn new(feature: &str, force_dir: &Path, worktree: PathBuf) -> Self {
+    pub fn new(
+        feature: &str,
+        force_dir: &Path,
+        worktree: PathBuf,
+        port_offset: u16,
+        ports: &PortsConfig,
+    ) -> Result<Self, String> {
         let feature_slug = slugify(feature);
-        let port_offset = hash_to_offset(feature);
-        let port = BASE_PORT + port_offset;
-
-        // Try to get project name from parent of .force/
-        let project_name = force_dir
-            .parent()
-            .and_then(|p| p.file_name())
-            .and_then(|n| n.to_str())
-            .unwrap_or("app");
+        let (port, ports) = ports.session_ports(port_offset)?;
 
-        let db_name = format!("{}_{}", slugify(project_name), feature_slug);
+        let project_slug = match slugify(project_name(force_dir)) {
+            slug if slug.is_empty() => "app".to_string(),
+            slug => slug,
+        };
+        let db_name = format!("{}_{}", project_slug, feature_slug);
 
-        Self {
+        Ok(Self {
             feature: feature.to_string(),
             feature_slug,
             port_offset,
             port,
+            ports,
             db_name,
             force_dir: force_dir.to_path_buf(),
             worktree,
+            vars: Vec::new(),
+        })
+    }
+
+    /// The environment a session's scripts received, from the values
+    /// recorded at `force up` rather than the current config
+    pub fn for_session(session: &Session, force_dir: &Path) -> Self {
+        Self {
+            feature: session.feature.clone(),
+            feature_slug: session.slug.clone(),
+            port_offset: session.port_offset,
+            port: session.port,
+            ports: session
+                .ports
+                .iter()
+                .map(|(name, port)| (name.clone(), *port))
+                .collect(),
+            db_name: recorded_db_name(session, force_dir),
+            force_dir: force_dir.to_path_buf(),
+            worktree: session.worktree.clone(),
+            vars: Vec::new(),
+        }
+    }
+
+    /// Use the slug and database name an existing session was created with,
+    /// which may predate changes to `slugify`
+    pub fn with_recorded(mut self, session: Option<&Session>) -> Self {
+        if let Some(session) = session {
+            self.feature_slug = session.slug.clone();
+            self.db_name = recorded_db_name(session, &self.force_dir);
         }
+        self
+    }
+
+    /// Add custom variables, expanding references to the variables already
+    /// set. Custom variables with the same name are replaced.
+    pub fn with_vars(mut self, vars: &BTreeMap<String, String>) -> Result<Self, String> {
+        let current = self.to_env_vars();
+        for (name, template) in vars {
+            let value = expand(template, &current)?;
+            self.vars.retain(|(existing, _)| existing != name);
+            self.vars.push((name.clone(), value));
+        }
+        Ok(self)
+    }
+
+    /// Add the built-in values of `old` as `FORCE_OLD_*`, for `[rename]`
+    /// hooks
+    pub fn with_old(mut self, old: &ForceEnv) -> Self {
+        self.vars.extend(
+            old.to_env_vars()
+                .into_iter()
+                .filter_map(|(name, value)| Some((old_var_name(&name)?, value))),
+        );
+        self
     }
 
     /// Convert to environment variable pairs
     pub fn to_env_vars(&self) -> Vec<(String, String)> {
-        vec![
+        let mut vars = vec![
             ("FORCE_FEATURE".to_string(), self.feature.clone()),
             ("FORCE_FEATURE_SLUG".to_string(), self.feature_slug.clone()),
             (
@@ -58,12 +121,177 @@ impl ForceEnv {
                 "FORCE_WORKTREE".to_string(),
                 self.worktree.display().to_string(),
             ),
-        ]
+        ];
+        for (name, port) in &self.ports {
+            vars.push((
+                format!("FORCE_PORT_{}", name.to_ascii_uppercase()),
+                port.to_string(),
+            ));
+        }
+        vars.extend(self.vars.iter().cloned());
+        vars
+    }
+}
+
+/// Names of the built-in variables exported for sessions using `ports`
+pub fn builtin_var_names(ports: &PortsConfig) -> Vec<String> {
+    ForceEnv::new("", Path::new(""), PathBuf::new(), 0, ports)
+        .expect("an offset of 0 can't overflow")
+        .to_env_vars()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Names of the `FORCE_OLD_*` variables exported to `[rename]` hooks
+pub fn old_var_names(ports: &PortsConfig) -> Vec<String> {
+    builtin_var_names(ports)
+        .iter()
+        .filter_map(|name| old_var_name(name))
+        .collect()
+}
+
+fn old_var_name(name: &str) -> Option<String> {
+    name.strip_prefix("FORCE_")
+        .map(|rest| format!("FORCE_OLD_{}", rest))
+}
+
+/// Output formats for `force env`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum EnvFormat {
+    Bash,
+    Zsh,
+    Fish,
+    Dotenv,
+    Json,
+}
+
+/// Render environment variables so a shell or tool can load them
+pub fn render(vars: &[(String, String)], format: EnvFormat) -> String {
+    match format {
+        EnvFormat::Bash | EnvFormat::Zsh => vars
+            .iter()
+            .map(|(key, value)| format!("export {}='{}'\n", key, value.replace('\'', "'\\''")))
+            .collect(),
+        EnvFormat::Fish => vars
+            .iter()
+            .map(|(key, value)| {
+                let value = value.replace('\\', "\\\\").replace('\'', "\\'");
+                format!("set -gx {} '{}'\n", key, value)
+            })
+            .collect(),
+        EnvFormat::Dotenv => vars
+            .iter()
+            .map(|(key, value)| format!("{}={}\n", key, dotenv_quote(value)))
+            .collect(),
+        EnvFormat::Json => {
+            let object: serde_json::Map<String, serde_json::Value> = vars
+                .iter()
+                .map(|(key, value)| (key.clone(), serde_json::Value::from(value.as_str())))
+                .collect();
+            let json = serde_json::to_string_pretty(&object).expect("strings always serialize");
+            format!("{}\n", json)
+        }
+    }
+}
+
+/// Quote a value for a dotenv file.
+///
+/// Values with `$` use single quotes where possible, since most dotenv
+/// loaders interpolate variables inside double quotes.
+fn dotenv_quote(value: &str) -> String {
+    if value.contains('$') && !value.contains(['\'', '\n']) {
+        return format!("'{}'", value);
     }
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
 }
 
-/// Convert a feature name to a slug (lowercase ASCII, underscores)
+/// Expand `$VAR` and `${VAR}` references using `vars`. `$$` is a literal `$`.
+///
+/// Unknown variables are an error rather than expanding to nothing, so a typo
+/// doesn't silently produce a wrong value.
+pub fn expand(template: &str, vars: &[(String, String)]) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(escaped) = after.strip_prefix('$') {
+            result.push('$');
+            rest = escaped;
+            continue;
+        }
+
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| format!("Unclosed '${{' in '{}'", template))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        if name.is_empty() {
+            // A lone `$` is kept as-is
+            result.push('$');
+            rest = after;
+            continue;
+        }
+
+        let value = vars
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| format!("Unknown variable '{}' in '{}'", name, template))?;
+        result.push_str(value);
+        rest = remainder;
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Convert a feature name to a slug (lowercase ASCII, underscores).
+/// Accented Latin letters are transliterated, and each run of other
+/// characters becomes a single underscore between words.
 pub fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    let mut separator = false;
+    for c in name.chars() {
+        // Combining accents, as in a decomposed "é"
+        if ('\u{0300}'..='\u{036F}').contains(&c) {
+            continue;
+        }
+        let mut buf = [0; 4];
+        let letters: &str = if c.is_ascii_alphanumeric() {
+            c.to_ascii_lowercase().encode_utf8(&mut buf)
+        } else if let Some(ascii) = transliterate(c) {
+            ascii
+        } else {
+            separator = true;
+            continue;
+        };
+        if separator && !slug.is_empty() {
+            slug.push('_');
+        }
+        separator = false;
+        slug.push_str(letters);
+    }
+    slug
+}
+
+/// The slug rule before transliteration: every character that isn't ASCII
+/// alphanumeric becomes `_`. Sessions created back then keep these slugs.
+pub fn legacy_slugify(name: &str) -> String {
     name.chars()
         .map(|c| {
             if c.is_ascii_alphanumeric() {
@@ -75,12 +303,77 @@ pub fn slugify(name: &str) -> String {
         .collect()
 }
 
-/// Hash a feature name to a port offset (0-999)
-fn hash_to_offset(feature: &str) -> u16 {
+/// The session's FORCE_DB_NAME, rebuilt with the old slug rule for sessions
+/// from before database names were recorded
+fn recorded_db_name(session: &Session, force_dir: &Path) -> String {
+    session.db_name.clone().unwrap_or_else(|| {
+        format!(
+            "{}_{}",
+            legacy_slugify(project_name(force_dir)),
+            session.slug
+        )
+    })
+}
+
+/// Project name from the parent of .force/
+fn project_name(force_dir: &Path) -> &str {
+    force_dir
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("app")
+}
+
+/// Slug for a new feature name, refusing names it would leave empty
+pub fn feature_slug(feature: &str) -> Result<String, String> {
+    let slug = slugify(feature);
+    if slug.is_empty() {
+        return Err(format!(
+            "Feature name '{}' has no letters or digits to build a slug from",
+            feature
+        ));
+    }
+    Ok(slug)
+}
+
+/// Lowercase ASCII spelling of a Latin-1 or Latin Extended-A letter
+fn transliterate(c: char) -> Option<&'static str> {
+    let ascii = match c {
+        'À'..='Å' | 'à'..='å' | '\u{0100}'..='\u{0105}' => "a",
+        'Æ' | 'æ' => "ae",
+        'Ç' | 'ç' | '\u{0106}'..='\u{010D}' => "c",
+        'Ð' | 'ð' | '\u{010E}'..='\u{0111}' => "d",
+        'È'..='Ë' | 'è'..='ë' | '\u{0112}'..='\u{011B}' => "e",
+        '\u{011C}'..='\u{0123}' => "g",
+        '\u{0124}'..='\u{0127}' => "h",
+        'Ì'..='Ï' | 'ì'..='ï' | '\u{0128}'..='\u{0131}' => "i",
+        '\u{0132}' | '\u{0133}' => "ij",
+        '\u{0134}' | '\u{0135}' => "j",
+        '\u{0136}'..='\u{0138}' => "k",
+        '\u{0139}'..='\u{0142}' => "l",
+        'Ñ' | 'ñ' | '\u{0143}'..='\u{014B}' => "n",
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' | '\u{014C}'..='\u{0151}' => "o",
+        '\u{0152}' | '\u{0153}' => "oe",
+        '\u{0154}'..='\u{0159}' => "r",
+        'ß' => "ss",
+        '\u{015A}'..='\u{0161}' | '\u{017F}' => "s",
+        'Þ' | 'þ' => "th",
+        '\u{0162}'..='\u{0167}' => "t",
+        'Ù'..='Ü' | 'ù'..='ü' | '\u{0168}'..='\u{0173}' => "u",
+        '\u{0174}' | '\u{0175}' => "w",
+        'Ý' | 'ý' | 'ÿ' | '\u{0176}'..='\u{0178}' => "y",
+        '\u{0179}'..='\u{017E}' => "z",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+/// Hash a feature name to its preferred port offset (0..range)
+pub fn hash_to_offset(feature: &str, range: u16) -> u16 {
     let hash: u32 = feature
         .bytes()
         .fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32));
-    (hash % 1000) as u16
+    (hash % range as u32) as u16
 }
 
 #[cfg(test)]
@@ -89,6 +382,132 @@ mod tests {
     use proptest::prelude::*;
     use std::path::PathBuf;
 
+    fn quoting_vars() -> Vec<(String, String)> {
+        vec![
+            ("FORCE_PORT".to_string(), "4123".to_string()),
+            (
+                "FORCE_FEATURE".to_string(),
+                "it's \"quoted\" \\ $HOME".to_string(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_render_bash() {
+        assert_eq!(
+            render(&quoting_vars(), EnvFormat::Bash),
+            "export FORCE_PORT='4123'\nexport FORCE_FEATURE='it'\\''s \"quoted\" \\ $HOME'\n"
+        );
+        assert_eq!(
+            render(&quoting_vars(), EnvFormat::Zsh),
+            render(&quoting_vars(), EnvFormat::Bash)
+        );
+    }
+
+    #[test]
+    fn test_render_fish() {
+        assert_eq!(
+            render(&quoting_vars(), EnvFormat::Fish),
+            "set -gx FORCE_PORT '4123'\nset -gx FORCE_FEATURE 'it\\'s \"quoted\" \\\\ $HOME'\n"
+        );
+    }
+
+    #[test]
+    fn test_render_dotenv() {
+        assert_eq!(
+            render(&quoting_vars(), EnvFormat::Dotenv),
+            "FORCE_PORT=\"4123\"\nFORCE_FEATURE=\"it's \\\"quoted\\\" \\\\ $HOME\"\n"
+        );
+    }
+
+    #[test]
+    fn test_dotenv_quote() {
+        assert_eq!(dotenv_quote("plain"), "\"plain\"");
+        assert_eq!(dotenv_quote("pa$$word"), "'pa$$word'");
+        assert_eq!(
+            dotenv_quote("line one\nline two"),
+            "\"line one\\nline two\""
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&quoting_vars(), EnvFormat::Json)).unwrap();
+        assert_eq!(json["FORCE_PORT"], "4123");
+        assert_eq!(json["FORCE_FEATURE"], "it's \"quoted\" \\ $HOME");
+    }
+
+    #[test]
+    fn test_with_vars_expands_and_overrides() {
+        let env = ForceEnv::new(
+            "login",
+            Path::new("/tmp/myapp/.force"),
+            PathBuf::from("/tmp/worktrees/login"),
+            0,
+            &PortsConfig::default(),
+        )
+        .unwrap();
+        let config_vars = BTreeMap::from([(
+            "DATABASE_URL".to_string(),
+            "postgres://localhost/${FORCE_DB_NAME}".to_string(),
+        )]);
+        let script_vars = BTreeMap::from([(
+            "DATABASE_URL".to_string(),
+            "${DATABASE_URL}_test".to_string(),
+        )]);
+
+        let env = env.with_vars(&config_vars).unwrap();
+        assert!(env.to_env_vars().contains(&(
+            "DATABASE_URL".to_string(),
+            "postgres://localhost/myapp_login".to_string()
+        )));
+
+        let env = env.with_vars(&script_vars).unwrap();
+        let urls: Vec<_> = env
+            .to_env_vars()
+            .into_iter()
+            .filter(|(name, _)| name == "DATABASE_URL")
+            .collect();
+        assert_eq!(
+            urls,
+            vec![(
+                "DATABASE_URL".to_string(),
+                "postgres://localhost/myapp_login_test".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_builtin_var_names_include_named_ports() {
+        let mut ports = PortsConfig::default();
+        ports.named.insert("api".to_string(), 5000);
+        let names = builtin_var_names(&ports);
+        assert!(names.contains(&"FORCE_DB_NAME".to_string()));
+        assert!(names.contains(&"FORCE_PORT_API".to_string()));
+    }
+
+    #[test]
+    fn test_expand() {
+        let vars = vec![
+            ("FORCE_PORT".to_string(), "4123".to_string()),
+            ("FORCE_FEATURE".to_string(), "login".to_string()),
+        ];
+        assert_eq!(expand("$FORCE_PORT", &vars).unwrap(), "4123");
+        assert_eq!(
+            expand("http://localhost:${FORCE_PORT}/health", &vars).unwrap(),
+            "http://localhost:4123/health"
+        );
+        assert_eq!(
+            expand("$FORCE_FEATURE-$FORCE_PORT", &vars).unwrap(),
+            "login-4123"
+        );
+        assert_eq!(expand("cost: $ 5", &vars).unwrap(), "cost: $ 5");
+        assert_eq!(expand("$$HOME/$FORCE_PORT", &vars).unwrap(), "$HOME/4123");
+        assert!(expand("$FORCE_PROT", &vars).is_err());
+        assert!(expand("${FORCE_PORT", &vars).is_err());
+    }
+
     #[test]
     fn test_slugify() {
         assert_eq!(slugify("add-login"), "add_login");
@@ -106,22 +525,46 @@ mod tests {
         assert_eq!(slugify("a@b#c$d"), "a_b_c_d");
     }
 
+    #[test]
+    fn test_slugify_collapses_and_trims_separators() {
+        assert_eq!(slugify("add--login"), "add_login");
+        assert_eq!(slugify("  Fix: the  bug! "), "fix_the_bug");
+        assert_eq!(slugify("_private_"), "private");
+    }
+
+    #[test]
+    fn test_slugify_transliterates() {
+        assert_eq!(slugify("café-menü"), "cafe_menu");
+        assert_eq!(slugify("Straße"), "strasse");
+        assert_eq!(slugify("Łódź Œuvre"), "lodz_oeuvre");
+        // "é" written as "e" plus a combining accent
+        assert_eq!(slugify("cafe\u{0301}"), "cafe");
+        assert_eq!(slugify("日本語"), "");
+    }
+
+    #[test]
+    fn test_feature_slug_rejects_empty() {
+        assert_eq!(feature_slug("café").unwrap(), "cafe");
+        assert!(feature_slug("日本語").is_err());
+        assert!(feature_slug("--").is_err());
+    }
+
     #[test]
     fn test_hash_is_deterministic() {
-        let offset1 = hash_to_offset("my-feature");
-        let offset2 = hash_to_offset("my-feature");
+        let offset1 = hash_to_offset("my-feature", 1000);
+        let offset2 = hash_to_offset("my-feature", 1000);
         assert_eq!(offset1, offset2);
     }
 
     #[test]
     fn test_hash_is_in_range() {
-        let offset = hash_to_offset("some-random-feature-name");
+        let offset = hash_to_offset("some-random-feature-name", 1000);
         assert!(offset < 1000);
     }
 
     #[test]
     fn test_hash_empty_string() {
-        let offset = hash_to_offset("");
+        let offset = hash_to_offset("", 1000);
         assert!(offset < 1000);
     }
 
@@ -131,7 +574,10 @@ mod tests {
             "my-feature",
             &PathBuf::from("/project/.force"),
             PathBuf::from("/project/worktrees/my_feature"),
-        );
+            hash_to_offset("my-feature", 1000),
+            &PortsConfig::default(),
+        )
+        .unwrap();
         let vars = env.to_env_vars();
 
         assert_eq!(vars.len(), 7);
@@ -161,22 +607,103 @@ mod tests {
             "add-login",
             &PathBuf::from("/myproject/.force"),
             PathBuf::from("/myproject/worktrees/add_login"),
-        );
+            0,
+            &PortsConfig::default(),
+        )
+        .unwrap();
         assert_eq!(env.db_name, "myproject_add_login");
     }
 
+    #[test]
+    fn test_force_env_with_recorded_keeps_old_slug_and_db_name() {
+        let new_env = || {
+            ForceEnv::new(
+                "fix--bug",
+                &PathBuf::from("/my--app/.force"),
+                PathBuf::from("/my--app/worktrees/fix__bug"),
+                0,
+                &PortsConfig::default(),
+            )
+            .unwrap()
+        };
+        assert_eq!(new_env().feature_slug, "fix_bug");
+        assert_eq!(new_env().db_name, "my_app_fix_bug");
+
+        let mut session = Session::new(&new_env(), "fix__bug");
+        session.slug = "fix__bug".to_string();
+        session.db_name = None;
+        let env = new_env().with_recorded(Some(&session));
+        assert_eq!(env.feature_slug, "fix__bug");
+        assert_eq!(env.db_name, "my__app_fix__bug");
+
+        session.db_name = Some("recorded_db".to_string());
+        let env = new_env().with_recorded(Some(&session));
+        assert_eq!(env.db_name, "recorded_db");
+        assert_eq!(new_env().with_recorded(None).feature_slug, "fix_bug");
+    }
+
+    #[test]
+    fn test_force_env_uses_given_offset() {
+        let env = ForceEnv::new(
+            "add-login",
+            &PathBuf::from("/myproject/.force"),
+            PathBuf::from("/myproject/worktrees/add_login"),
+            42,
+            &PortsConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(env.port_offset, 42);
+        assert_eq!(env.port, 4042);
+    }
+
+    #[test]
+    fn test_force_env_rejects_offset_past_last_port() {
+        let mut ports = PortsConfig::default();
+        ports.named.insert("web".to_string(), 65000);
+
+        let err = ForceEnv::new(
+            "add-login",
+            &PathBuf::from("/myproject/.force"),
+            PathBuf::from("/myproject/worktrees/add_login"),
+            999,
+            &ports,
+        )
+        .err()
+        .unwrap();
+        assert!(err.contains("'web' (65000) past 65535"), "{}", err);
+    }
+
+    #[test]
+    fn test_force_env_named_ports() {
+        let mut ports = PortsConfig::default();
+        ports.named.insert("web".to_string(), 4000);
+        ports.named.insert("api".to_string(), 5000);
+
+        let env = ForceEnv::new(
+            "add-login",
+            &PathBuf::from("/myproject/.force"),
+            PathBuf::from("/myproject/worktrees/add_login"),
+            42,
+            &ports,
+        )
+        .unwrap();
+        let var_map: std::collections::HashMap<_, _> = env.to_env_vars().into_iter().collect();
+        assert_eq!(var_map.get("FORCE_PORT_WEB"), Some(&"4042".to_string()));
+        assert_eq!(var_map.get("FORCE_PORT_API"), Some(&"5042".to_string()));
+    }
+
     // Property-based tests
     proptest! {
         #[test]
         fn prop_hash_always_in_range(s in ".*") {
-            let offset = hash_to_offset(&s);
+            let offset = hash_to_offset(&s, 1000);
             prop_assert!(offset < 1000);
         }
 
         #[test]
         fn prop_hash_is_deterministic(s in ".*") {
-            let offset1 = hash_to_offset(&s);
-            let offset2 = hash_to_offset(&s);
+            let offset1 = hash_to_offset(&s, 1000);
+            let offset2 = hash_to_offset(&s, 1000);
             prop_assert_eq!(offset1, offset2);
         }
 
@@ -189,12 +716,40 @@ mod tests {
         }
 
         #[test]
-        fn prop_slugify_same_length(s in "[a-zA-Z0-9_ \\-]{0,100}") {
-            // For ASCII-only input, slugify preserves character count
+        fn prop_slugify_same_length(s in "[a-zA-Z0-9]{0,100}") {
+            // For ASCII letters and digits, slugify preserves character count
             let slug = slugify(&s);
             prop_assert_eq!(slug.chars().count(), s.chars().count());
         }
 
+        #[test]
+        fn prop_slugify_single_inner_separators(s in ".*") {
+            let slug = slugify(&s);
+            prop_assert!(!slug.starts_with('_'));
+            prop_assert!(!slug.ends_with('_'));
+            prop_assert!(!slug.contains("__"));
+        }
+
+        #[test]
+        fn prop_slugify_keeps_words(words in proptest::collection::vec("[a-z0-9]{1,10}", 1..5), sep in "[ _\\-.@]{1,3}") {
+            // Words survive any run of separators, joined by one underscore
+            prop_assert_eq!(slugify(&words.join(&sep)), words.join("_"));
+        }
+
+        #[test]
+        fn prop_slugify_empty_only_without_letters(s in ".*") {
+            let has_ascii_alphanumeric = s.chars().any(|c| c.is_ascii_alphanumeric());
+            prop_assert!(!has_ascii_alphanumeric || !slugify(&s).is_empty());
+            prop_assert_eq!(feature_slug(&s).is_err(), slugify(&s).is_empty());
+        }
+
+        #[test]
+        fn prop_slugify_transliterates_latin(s in "[a-zà-öø-ÿ]{1,20}") {
+            // Accented lowercase letters map to the same slug as uppercase ones
+            prop_assert_eq!(slugify(&s), slugify(&s.to_uppercase()));
+            prop_assert!(!slugify(&s).is_empty());
+        }
+
         #[test]
         fn prop_slugify_is_idempotent(s in ".*") {
             let slug1 = slugify(&s);
@@ -203,14 +758,31 @@ mod tests {
         }
 
         #[test]
-        fn prop_port_in_valid_range(feature in "[a-zA-Z][a-zA-Z0-9\\-]{0,50}") {
+        fn prop_hash_in_configured_range(s in ".*", range in 1u16..=u16::MAX) {
+            prop_assert!(hash_to_offset(&s, range) < range);
+        }
+
+        #[test]
+        fn prop_port_in_valid_range(
+            feature in "[a-zA-Z][a-zA-Z0-9\\-]{0,50}",
+            base in 1024u16..60000,
+            range in 1u16..5000,
+        ) {
+            let ports = PortsConfig {
+                base,
+                range,
+                ..PortsConfig::default()
+            };
             let env = ForceEnv::new(
                 &feature,
                 &PathBuf::from("/test/.force"),
                 PathBuf::from("/test/worktrees/feature"),
-            );
-            prop_assert!(env.port >= 4000);
-            prop_assert!(env.port < 5000);
+                hash_to_offset(&feature, ports.range),
+                &ports,
+            )
+        .unwrap();
+            prop_assert!(env.port >= base);
+            prop_assert!(env.port < base + range);
         }
     }
 }
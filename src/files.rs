@@ -0,0 +1,301 @@
+use crate::config::FileTemplate;
+use crate::env::{self, EnvFormat, ForceEnv};
+use crate::state::GeneratedFile;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Render a template into its target path and content
+pub fn render(
+    template: &FileTemplate,
+    env: &ForceEnv,
+) -> Result<(PathBuf, String), Box<dyn std::error::Error>> {
+    let vars = env.to_env_vars();
+    let expand = |text: &str| {
+        env::expand(text, &vars).map_err(|e| format!("[[files]] '{}': {}", template.path, e))
+    };
+
+    let path = env.worktree.join(expand(&template.path)?);
+    let content = if let Some(content) = &template.content {
+        expand(content)?
+    } else if let Some(source) = &template.template {
+        let source = env.force_dir.join(source);
+        let content = fs::read_to_string(&source)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        expand(&content)?
+    } else {
+        let values = template
+            .env
+            .as_ref()
+            .ok_or_else(|| format!("[[files]] '{}' has no content", template.path))?;
+        let expanded = values
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), expand(value)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        env::render(&expanded, EnvFormat::Dotenv)
+    };
+
+    Ok((path, content))
+}
+
+/// Write templates into the worktree.
+///
+/// An existing file is only overwritten if Force generated it (listed in
+/// `previous`) and it hasn't been edited since. Returns the files written.
+pub fn write_files(
+    templates: &[&FileTemplate],
+    env: &ForceEnv,
+    previous: &[GeneratedFile],
+) -> Result<Vec<GeneratedFile>, Box<dyn std::error::Error>> {
+    let mut written = Vec::new();
+
+    for template in templates {
+        let (path, content) = render(template, env)?;
+
+        if path.exists() {
+            let ours = previous
+                .iter()
+                .any(|file| file.path == path && is_unchanged(file));
+            if !ours {
+                println!(
+                    "Not writing {} (file exists and wasn't generated by Force)",
+                    path.display()
+                );
+                continue;
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_with_mode(&path, &content, template.mode()?)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        println!("Wrote {}", path.display());
+        written.push(GeneratedFile {
+            path,
+            hash: content_hash(content.as_bytes()),
+        });
+    }
+
+    Ok(written)
+}
+
+/// Write a file so its content is never readable beyond `mode`: a new file is
+/// created with it, and an existing one is narrowed before it is truncated
+fn write_with_mode(path: &Path, content: &str, mode: Option<u32>) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    if let Some(mode) = mode {
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+        options.mode(mode);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(content.as_bytes())?;
+    // The umask may have narrowed the mode on creation
+    if let Some(mode) = mode {
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+/// Remove a generated file unless it was edited after Force wrote it.
+/// Returns whether the file was removed.
+pub fn remove_file(file: &GeneratedFile) -> Result<bool, Box<dyn std::error::Error>> {
+    if !file.path.exists() {
+        return Ok(false);
+    }
+    if !is_unchanged(file) {
+        return Ok(false);
+    }
+    fs::remove_file(&file.path)
+        .map_err(|e| format!("Failed to remove {}: {}", file.path.display(), e))?;
+    Ok(true)
+}
+
+/// Remove generated files, reporting any that were kept because they changed
+pub fn remove_files(files: &[GeneratedFile]) -> Result<(), Box<dyn std::error::Error>> {
+    for file in files {
+        if remove_file(file)? {
+            println!("Removed {}", file.path.display());
+        } else if file.path.exists() {
+            println!("Keeping {} (modified since force up)", file.path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a file still has the content (or symlink target) Force recorded
+pub fn is_unchanged(file: &GeneratedFile) -> bool {
+    path_hash(&file.path).is_some_and(|hash| hash == file.hash)
+}
+
+/// Hash of a file's content or a symlink's target. A directory hashes the
+/// path, size and modification time of everything inside it, which stays
+/// cheap for trees like node_modules.
+pub fn path_hash(path: &Path) -> Option<String> {
+    let metadata = path.symlink_metadata().ok()?;
+    if metadata.is_symlink() {
+        let target = fs::read_link(path).ok()?;
+        let mut bytes = b"symlink:".to_vec();
+        bytes.extend(target.as_os_str().as_bytes());
+        return Some(content_hash(&bytes));
+    }
+    if metadata.is_dir() {
+        let mut bytes = b"dir:".to_vec();
+        tree_signature(path, Path::new(""), &mut bytes).ok()?;
+        return Some(content_hash(&bytes));
+    }
+    fs::read(path).ok().map(|content| content_hash(&content))
+}
+
+/// Append each entry under `dir` to `out`, in name order
+fn tree_signature(dir: &Path, relative: &Path, out: &mut Vec<u8>) -> std::io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let relative = relative.join(entry.file_name());
+        let metadata = path.symlink_metadata()?;
+        out.extend(relative.as_os_str().as_bytes());
+        if metadata.is_dir() {
+            out.push(b'/');
+            tree_signature(&path, &relative, out)?;
+        } else if metadata.is_symlink() {
+            out.extend(b" -> ");
+            out.extend(fs::read_link(&path)?.as_os_str().as_bytes());
+        } else {
+            let stamp = format!(
+                " {} {}.{}",
+                metadata.size(),
+                metadata.mtime(),
+                metadata.mtime_nsec()
+            );
+            out.extend(stamp.as_bytes());
+        }
+        out.push(0);
+    }
+    Ok(())
+}
+
+/// FNV-1a hash of file content, stable across Force versions
+fn content_hash(content: &[u8]) -> String {
+    let hash = content.iter().fold(0xcbf29ce484222325u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Group templates by target so later ones (scripts) override earlier ones
+/// (config.toml) for the same path
+pub fn merge<'a>(templates: impl IntoIterator<Item = &'a FileTemplate>) -> Vec<&'a FileTemplate> {
+    let mut by_path: BTreeMap<&Path, &FileTemplate> = BTreeMap::new();
+    for template in templates {
+        by_path.insert(Path::new(&template.path), template);
+    }
+    by_path.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PortsConfig;
+    use tempfile::TempDir;
+
+    fn test_env(dir: &Path) -> ForceEnv {
+        ForceEnv::new(
+            "files-test",
+            &dir.join(".force"),
+            dir.to_path_buf(),
+            0,
+            &PortsConfig::default(),
+        )
+        .unwrap()
+    }
+
+    fn env_template(path: &str) -> FileTemplate {
+        FileTemplate {
+            path: path.to_string(),
+            content: None,
+            template: None,
+            env: Some(BTreeMap::from([
+                ("PORT".to_string(), "$FORCE_PORT".to_string()),
+                ("SECRET".to_string(), "pa$$word".to_string()),
+            ])),
+            mode: Some("600".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_render_env_file() {
+        let dir = TempDir::new().unwrap();
+        let env = test_env(dir.path());
+        let (path, content) = render(&env_template(".env"), &env).unwrap();
+        assert_eq!(path, dir.path().join(".env"));
+        assert_eq!(
+            content,
+            format!("PORT=\"{}\"\nSECRET='pa$word'\n", env.port)
+        );
+    }
+
+    #[test]
+    fn test_write_and_remove_files() {
+        let dir = TempDir::new().unwrap();
+        let env = test_env(dir.path());
+        let template = env_template("config/.env");
+
+        let written = write_files(&[&template], &env, &[]).unwrap();
+        assert_eq!(written.len(), 1);
+        let path = dir.path().join("config/.env");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        assert!(remove_file(&written[0]).unwrap());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_remove_keeps_edited_file() {
+        let dir = TempDir::new().unwrap();
+        let env = test_env(dir.path());
+        let written = write_files(&[&env_template(".env")], &env, &[]).unwrap();
+
+        fs::write(dir.path().join(".env"), "EDITED=1\n").unwrap();
+        assert!(!remove_file(&written[0]).unwrap());
+        assert!(dir.path().join(".env").exists());
+    }
+
+    #[test]
+    fn test_write_skips_files_not_generated_by_force() {
+        let dir = TempDir::new().unwrap();
+        let env = test_env(dir.path());
+        fs::write(dir.path().join(".env"), "MINE=1\n").unwrap();
+
+        let written = write_files(&[&env_template(".env")], &env, &[]).unwrap();
+        assert!(written.is_empty());
+        assert_eq!(
+            fs::read_to_string(dir.path().join(".env")).unwrap(),
+            "MINE=1\n"
+        );
+    }
+
+    #[test]
+    fn test_write_overwrites_unchanged_generated_file() {
+        let dir = TempDir::new().unwrap();
+        let env = test_env(dir.path());
+        let previous = write_files(&[&env_template(".env")], &env, &[]).unwrap();
+        let path = dir.path().join(".env");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let written = write_files(&[&env_template(".env")], &env, &previous).unwrap();
+        assert_eq!(written, previous);
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
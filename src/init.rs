@@ -11,34 +11,66 @@ const CONFIG_EXAMPLE: &str = r#"# Force Configuration
 
 # Remove worktree when running `force down` (default: true)
 # remove_on_down = true
+
+# Branch name template for new worktrees (default: "$FORCE_FEATURE_SLUG")
+# branch = "agent/$FORCE_FEATURE_SLUG"
+
+# Ref new branches start from (default: the current HEAD)
+# base = "origin/main"
+
+# Delete the branch on `force down`: "never", "merged" (into base), or "always"
+# delete_branch = "never"
+
+# Ignored files copied or symlinked from the main checkout (glob patterns)
+# copy = [".env", "config/master.key"]
+# link = ["node_modules"]
+
+# Check out submodules and fetch Git LFS objects in new worktrees (default: false)
+# submodules = true
+# lfs = true
+
+# [ports]
+# First port handed out and number of offsets (defaults shown)
+# base = 4000
+# range = 1000
+#
+# Named ports, exported as FORCE_PORT_<NAME> (base + the session's offset).
+# Keep them at least range apart from base and each other.
+# web = 4000
+# api = 5000
+
+# [env]
+# Extra variables for every script. Values can use the built-in FORCE_* variables.
+# DATABASE_URL = "postgres://localhost/${FORCE_DB_NAME}"
 "#;
 
 const ENV_EXAMPLE: &str = r#"# Force Script: env.toml
 #
-# Creates .dev.local.env and .test.local.env in the worktree
-# with PORT and DATABASE_URL for the feature branch.
+# Writes .dev.local.env and .test.local.env into the worktree
+# with PORT and DATABASE_URL for the feature branch. Force removes
+# them on `force down` unless they were edited.
 
 [meta]
 category = "setup"
 priority = 1
 
-[up]
-description = "Create local env files"
-run = """
-cat > .dev.local.env << EOF
-PORT=$FORCE_PORT
-DATABASE_URL=postgres://localhost/$FORCE_DB_NAME
-EOF
-
-cat > .test.local.env << EOF
-PORT=$FORCE_PORT
-DATABASE_URL=postgres://localhost/${FORCE_DB_NAME}_test
-EOF
-"""
+[[files]]
+path = ".dev.local.env"
 
-[down]
-description = "Remove local env files"
-run = "rm -f .dev.local.env .test.local.env"
+[files.env]
+PORT = "$FORCE_PORT"
+DATABASE_URL = "postgres://localhost/$FORCE_DB_NAME"
+
+[[files]]
+path = ".test.local.env"
+
+[files.env]
+PORT = "$FORCE_PORT"
+DATABASE_URL = "postgres://localhost/${FORCE_DB_NAME}_test"
+
+[up]
+description = "Show local env files"
+run = "cat .dev.local.env .test.local.env"
 "#;
 
 const DATABASE_EXAMPLE: &str = r#"# Force Script: database.toml
@@ -48,7 +80,7 @@ const DATABASE_EXAMPLE: &str = r#"# Force Script: database.toml
 #   FORCE_FEATURE      - Original feature name (e.g., "add-login")
 #   FORCE_FEATURE_SLUG - Sanitized name (e.g., "add_login")
 #   FORCE_PORT         - Assigned port (e.g., 4427)
-#   FORCE_PORT_OFFSET  - Port offset 0-999 (e.g., 427)
+#   FORCE_PORT_OFFSET  - Port offset within [ports] range (e.g., 427)
 #   FORCE_DB_NAME      - Database name (e.g., "myapp_add_login")
 #   FORCE_DIR          - Path to .force/ directory
 #   FORCE_WORKTREE     - Path to the worktree directory
@@ -1,11 +1,20 @@
 mod config;
+mod copy;
 mod env;
+mod files;
 mod init;
+mod ports;
+mod prune;
+mod ready;
 mod runner;
+mod service;
 mod state;
 mod worktree;
 
 use clap::{Parser, Subcommand};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process;
 
 #[derive(Parser)]
@@ -24,27 +33,158 @@ enum Commands {
     Up {
         /// Feature name for the session
         feature: String,
+        /// Leave completed scripts and the worktree in place if a script fails
+        #[arg(long)]
+        no_rollback: bool,
+        /// Run up to N independent scripts at once (1 runs everything in order)
+        #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+        /// Start a new branch from this ref instead of [worktree] base
+        #[arg(long, value_name = "REF", conflicts_with_all = ["branch", "from", "from_ref"])]
+        base: Option<String>,
+        /// Use this existing branch, or the worktree that has it checked out
+        #[arg(long, conflicts_with_all = ["from", "from_ref"])]
+        branch: Option<String>,
+        /// Start a new branch tracking this fetched remote branch, e.g. origin/login
+        #[arg(long, value_name = "REMOTE/BRANCH", conflicts_with = "from_ref")]
+        from: Option<String>,
+        /// Start a new branch from this fetched ref, e.g. refs/pull/12/head
+        #[arg(long, value_name = "REF")]
+        from_ref: Option<String>,
+    },
+    /// Start a session in an existing worktree
+    Adopt {
+        /// Path to a worktree of this repository
+        path: PathBuf,
+        /// Feature name for the session (default: the worktree's branch)
+        feature: Option<String>,
+        /// Leave completed scripts in place if a script fails
+        #[arg(long)]
+        no_rollback: bool,
+        /// Run up to N independent scripts at once (1 runs everything in order)
+        #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
     },
     /// Tear down a session (alias: d)
     #[command(alias = "d")]
     Down {
         /// Feature name for the session
         feature: String,
+        /// Remove the worktree even if that loses uncommitted or unpushed work
+        #[arg(long, conflicts_with_all = ["stash", "backup"])]
+        force: bool,
+        /// Stash uncommitted changes before removing the worktree
+        #[arg(long, conflicts_with = "backup")]
+        stash: bool,
+        /// Save uncommitted and unpushed work to refs/force/backup/<slug> first
+        #[arg(long)]
+        backup: bool,
+        /// Delete the worktree's branch (default: [worktree] delete_branch)
+        #[arg(long, value_name = "WHEN")]
+        delete_branch: Option<config::DeleteBranch>,
+    },
+    /// Rename a session, moving its worktree and branch
+    Rename {
+        /// Current feature name
+        old: String,
+        /// New feature name
+        new: String,
+        /// Assign a port for the new name instead of keeping the current one
+        #[arg(long)]
+        new_port: bool,
+    },
+    /// Print a session's environment variables, e.g. for `eval "$(force env <feature>)"`
+    Env {
+        /// Feature name for the session
+        feature: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = env::EnvFormat::Bash)]
+        format: env::EnvFormat,
+    },
+    /// Run a command in a session's worktree with its environment
+    Exec {
+        /// Feature name for the session
+        feature: String,
+        /// Command and arguments to run (after --)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Open $SHELL in a session's worktree with its environment
+    Shell {
+        /// Feature name for the session
+        feature: String,
     },
     /// Initialize a .force/ directory with example scripts
     Init,
     /// List active sessions
-    Ls,
+    Ls {
+        /// Also compare sessions with `git worktree list` and report problems
+        #[arg(long)]
+        check: bool,
+    },
+    /// Clean up sessions and worktrees that have drifted apart
+    Prune {
+        /// Clean up everything without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
 
     let result = match cli.command {
-        Commands::Up { feature } => run_up(&feature),
-        Commands::Down { feature } => run_down(&feature),
+        Commands::Up {
+            feature,
+            no_rollback,
+            jobs,
+            base,
+            branch,
+            from,
+            from_ref,
+        } => {
+            let start = match (&from, &from_ref) {
+                (Some(remote), _) => worktree::StartPoint::Remote(remote),
+                (_, Some(rev)) => worktree::StartPoint::Ref(rev),
+                _ => worktree::StartPoint::Base(base.as_deref()),
+            };
+            let source = WorktreeSource::Template {
+                start,
+                branch: branch.as_deref(),
+            };
+            run_up(&feature, no_rollback, jobs, source)
+        }
+        Commands::Adopt {
+            path,
+            feature,
+            no_rollback,
+            jobs,
+        } => run_adopt(&path, feature.as_deref(), no_rollback, jobs),
+        Commands::Down {
+            feature,
+            force,
+            stash,
+            backup,
+            delete_branch,
+        } => {
+            let on_unsaved = if force {
+                OnUnsaved::Discard
+            } else if stash {
+                OnUnsaved::Stash
+            } else if backup {
+                OnUnsaved::Backup
+            } else {
+                OnUnsaved::Refuse
+            };
+            run_down(&feature, on_unsaved, delete_branch)
+        }
+        Commands::Rename { old, new, new_port } => run_rename(&old, &new, new_port),
+        Commands::Env { feature, format } => run_env(&feature, format),
+        Commands::Exec { feature, command } => run_exec(&feature, &command),
+        Commands::Shell { feature } => run_shell(&feature),
         Commands::Init => init::run_init(),
-        Commands::Ls => run_ls(),
+        Commands::Ls { check } => run_ls(check),
+        Commands::Prune { yes } => run_prune(yes),
     };
 
     if let Err(e) = result {
@@ -53,7 +193,24 @@ fn main() {
     }
 }
 
-fn run_up(feature: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Where `force up` gets the session's worktree
+enum WorktreeSource<'a> {
+    /// The worktree at the path template, on a new branch from `start` or on
+    /// an existing `branch`
+    Template {
+        start: worktree::StartPoint<'a>,
+        branch: Option<&'a str>,
+    },
+    /// A worktree Force didn't create
+    Adopt(&'a Path),
+}
+
+fn run_up(
+    feature: &str,
+    no_rollback: bool,
+    jobs: Option<u16>,
+    source: WorktreeSource,
+) -> Result<(), Box<dyn std::error::Error>> {
     // 1. Find .force/ directory
     let force_dir = config::find_force_dir()?;
     println!("Found .force/ at: {}", force_dir.display());
@@ -64,19 +221,82 @@ fn run_up(feature: &str) -> Result<(), Box<dyn std::error::Error>> {
     // 3. Get project root (parent of .force/)
     let project_root = force_dir.parent().ok_or("Invalid .force/ location")?;
 
-    // 4. Create worktree
-    let feature_slug = env::slugify(feature);
-    let worktree_result =
-        worktree::create_worktree(project_root, &feature_slug, &force_config.worktree.path)?;
+    // 4. Discover and load scripts before touching any worktree, so a bad
+    // script leaves nothing behind
+    let scripts = config::load_scripts(&force_dir, &force_config)?;
+    println!("Found {} script(s)", scripts.len());
+
+    // 5. Create worktree, or reuse the session's or an existing one. The
+    // state stays locked until the session is registered, so concurrent runs
+    // can't claim the same slug or port offset
+    let state_lock = state::lock(&force_dir)?;
+    let existing = state::get_session(&force_dir, feature)?;
+    // Existing sessions keep the slug they were created with
+    let feature_slug = match &existing {
+        Some(session) => session.slug.clone(),
+        None => env::feature_slug(feature)?,
+    };
+    check_slug_available(&force_dir, feature, &feature_slug, None)?;
+    let port_offset = assign_port_offset(&force_dir, feature, &force_config.ports)?;
+    let worktree_result = resolve_up_worktree(
+        project_root,
+        &feature_slug,
+        &force_config.worktree,
+        existing.as_ref(),
+        source,
+    )?;
 
     if worktree_result.created {
         println!("Created worktree at: {}", worktree_result.path.display());
+    } else if worktree_result.adopted {
+        println!("Adopting worktree at: {}", worktree_result.path.display());
     } else {
         println!("Reusing worktree at: {}", worktree_result.path.display());
     }
 
-    // 5. Generate environment
-    let force_env = env::ForceEnv::new(feature, &force_dir, worktree_result.path);
+    // Until the scripts run, a failure only has the worktree and branch this
+    // run created to undo
+    let is_new_session = existing.is_none();
+    let base_env = env::ForceEnv::new(
+        feature,
+        &force_dir,
+        worktree_result.path.clone(),
+        port_offset,
+        &force_config.ports,
+    )?
+    .with_recorded(existing.as_ref());
+    let undo = |e: Box<dyn std::error::Error>| -> Box<dyn std::error::Error> {
+        if is_new_session && !no_rollback {
+            eprintln!(
+                "
+Setting up '{}' failed, rolling back...",
+                feature
+            );
+            rollback(
+                project_root,
+                &[],
+                &[],
+                &[],
+                &base_env,
+                worktree_result.created,
+                worktree_result
+                    .created_branch
+                    .then_some(worktree_result.branch.as_str()),
+            );
+            if let Err(e) = state::remove_session(&force_dir, feature) {
+                eprintln!("Failed to unregister session '{}': {}", feature, e);
+            }
+        }
+        e
+    };
+    let seeded = copy::seed_worktree(project_root, &worktree_result.path, &force_config.worktree)
+        .map_err(undo)?;
+
+    // 6. Generate environment
+    let force_env = base_env
+        .clone()
+        .with_vars(&force_config.env)
+        .map_err(|e| undo(e.into()))?;
     println!(
         "Feature: {} (slug: {})",
         force_env.feature, force_env.feature_slug
@@ -86,23 +306,218 @@ fn run_up(feature: &str) -> Result<(), Box<dyn std::error::Error>> {
         force_env.port, force_env.port_offset
     );
 
-    // 6. Discover and load scripts
-    let scripts = config::load_scripts(&force_dir)?;
-    println!("Found {} script(s)", scripts.len());
+    // 7. Register session as pending so an interrupted run stays visible
+    let created_branch =
+        worktree_result.created_branch || existing.as_ref().is_some_and(|s| s.created_branch);
+    let base = worktree_result
+        .base
+        .clone()
+        .or_else(|| existing.as_ref().and_then(|s| s.base.clone()));
+    let previous_files = existing
+        .as_ref()
+        .map(|s| s.files.clone())
+        .unwrap_or_default();
+    // Seeding skips paths that exist, so earlier records stay valid
+    let mut all_seeded: Vec<state::GeneratedFile> = existing
+        .iter()
+        .flat_map(|s| &s.seeded)
+        .filter(|old| !seeded.iter().any(|new| new.path == old.path))
+        .cloned()
+        .collect();
+    all_seeded.extend(seeded);
+    let mut session = match existing {
+        Some(existing) => {
+            // Services from the previous run are restarted by their scripts
+            runner::stop_services(&existing.services)?;
+            state::Session {
+                created_at: existing.created_at,
+                last_up_at: existing.last_up_at,
+                ..state::Session::new(&force_env, &worktree_result.branch)
+            }
+        }
+        None => state::Session::new(&force_env, &worktree_result.branch),
+    };
+    session.adopted = worktree_result.adopted;
+    session.created_branch = created_branch;
+    session.base = base;
+    session.seeded = all_seeded;
+    session.owner = Some(service::this_run());
+    state::save_session(&force_dir, &session).map_err(undo)?;
+    drop(state_lock);
 
-    // 7. Execute scripts in order
-    for script in scripts {
-        runner::run_script(&script, &force_env)?;
-    }
+    // 8. Render [[files]] templates, letting scripts override config.toml
+    let templates = file_templates(&force_config, &scripts);
+    session.files = files::write_files(&templates, &force_env, &previous_files).map_err(undo)?;
+    state::save_session(&force_dir, &session)?;
+
+    // 9. Execute scripts, running independent ones in parallel where allowed.
+    // --jobs N > 1 makes every category parallel; otherwise only categories
+    // marked `parallel = true` in config.toml run concurrently. Priorities
+    // within a category still run in order. Scripts mostly wait on I/O, so
+    // without --jobs there is no cap.
+    let all_parallel = jobs.is_some_and(|j| j > 1);
+    let jobs = jobs.map_or(scripts.len(), usize::from);
+    let result = runner::run_scripts(&scripts, &force_env, jobs, |category| {
+        all_parallel || force_config.is_parallel(category)
+    });
+    let services = match result {
+        Ok(services) => services,
+        Err(failure) => {
+            // Only roll back sessions this run created; an existing session
+            // may still be in use and its resources must not be torn down
+            if no_rollback || !is_new_session {
+                session.status = state::SessionStatus::Failed;
+                session.owner = None;
+                session.services = failure.services;
+                state::save_session(&force_dir, &session)?;
+                if no_rollback {
+                    println!("\nLeaving failed session in place (--no-rollback)");
+                }
+                return Err(failure.error.into());
+            }
 
-    // 8. Register session
-    state::add_session(&force_dir, feature)?;
+            eprintln!("\nScript '{}' failed, rolling back...", failure.failed);
+            let completed: Vec<&config::LoadedScript> =
+                failure.completed.iter().map(|&i| &scripts[i]).collect();
+            rollback(
+                project_root,
+                &completed,
+                &failure.services,
+                &session.files,
+                &force_env,
+                worktree_result.created,
+                worktree_result
+                    .created_branch
+                    .then_some(worktree_result.branch.as_str()),
+            );
+            state::remove_session(&force_dir, feature)?;
+            return Err(failure.error.into());
+        }
+    };
+
+    // 10. Mark session as up
+    session.services = services;
+    session.status = state::SessionStatus::Up;
+    session.owner = None;
+    session.last_up_at = Some(state::now());
+    state::save_session(&force_dir, &session)?;
 
     println!("\nSession '{}' is ready!", feature);
     Ok(())
 }
 
-fn run_down(feature: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// What `force down` does with work that removing the worktree would lose
+#[derive(Clone, Copy, PartialEq)]
+enum OnUnsaved {
+    Refuse,
+    Discard,
+    Stash,
+    Backup,
+}
+
+/// [[files]] templates from config.toml and scripts, scripts taking precedence
+fn file_templates<'a>(
+    force_config: &'a config::ForceConfig,
+    scripts: &'a [config::LoadedScript],
+) -> Vec<&'a config::FileTemplate> {
+    files::merge(
+        force_config
+            .files
+            .iter()
+            .chain(scripts.iter().flat_map(|s| &s.script.files)),
+    )
+}
+
+/// Pick the worktree for `force up`: an existing session keeps the worktree
+/// it recorded, even if the path template has changed since
+fn resolve_up_worktree(
+    project_root: &Path,
+    feature_slug: &str,
+    worktree_config: &config::WorktreeConfig,
+    existing: Option<&state::Session>,
+    source: WorktreeSource,
+) -> Result<worktree::WorktreeResult, Box<dyn std::error::Error>> {
+    let recorded = existing.filter(|s| worktree::is_valid_worktree(&s.worktree));
+
+    match source {
+        WorktreeSource::Adopt(path) => {
+            if let Some(session) = recorded
+                && !worktree::same_path(&session.worktree, path)
+            {
+                return Err(format!(
+                    "Session '{}' already uses the worktree at {}",
+                    session.feature,
+                    session.worktree.display()
+                )
+                .into());
+            }
+            worktree::adopt_worktree(project_root, path)
+        }
+        WorktreeSource::Template { start, branch } => {
+            if let Some(session) = recorded {
+                // The session's branch must already be where --from or
+                // --from-ref point, rather than have them silently ignored
+                worktree::require_start_point(project_root, &session.branch, start)?;
+                return Ok(worktree::WorktreeResult {
+                    path: session.worktree.clone(),
+                    branch: session.branch.clone(),
+                    created: false,
+                    adopted: session.adopted,
+                    created_branch: false,
+                    base: None,
+                });
+            }
+            if let Some(branch) = branch
+                && let Some(entry) = worktree::find_by_branch(project_root, branch)?
+            {
+                return worktree::adopt_worktree(project_root, &entry.path);
+            }
+            // A branch named with --branch already exists, so the new worktree
+            // reports `created_branch: false` and `force down` keeps the branch
+            let start = match start {
+                worktree::StartPoint::Base(None) => {
+                    worktree::StartPoint::Base(worktree_config.base.as_deref())
+                }
+                start => start,
+            };
+            worktree::create_worktree(project_root, feature_slug, worktree_config, start, branch)
+        }
+    }
+}
+
+/// Start a session in an existing worktree, named after its branch unless a
+/// feature name is given
+fn run_adopt(
+    path: &Path,
+    feature: Option<&str>,
+    no_rollback: bool,
+    jobs: Option<u16>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let feature = match feature {
+        Some(feature) => feature.to_string(),
+        None => {
+            let force_dir = config::find_force_dir()?;
+            let project_root = force_dir.parent().ok_or("Invalid .force/ location")?;
+            let adopted = worktree::adopt_worktree(project_root, path)?;
+            match adopted.branch.as_str() {
+                "HEAD" => return Err(format!(
+                    "The worktree at {} has no branch checked out; give a feature name: force adopt {} <feature>",
+                    path.display(),
+                    path.display()
+                )
+                .into()),
+                branch => branch.to_string(),
+            }
+        }
+    };
+    run_up(&feature, no_rollback, jobs, WorktreeSource::Adopt(path))
+}
+
+fn run_down(
+    feature: &str,
+    on_unsaved: OnUnsaved,
+    delete_branch: Option<config::DeleteBranch>,
+) -> Result<(), Box<dyn std::error::Error>> {
     // 1. Find .force/ directory
     let force_dir = config::find_force_dir()?;
     println!("Found .force/ at: {}", force_dir.display());
@@ -113,64 +528,725 @@ fn run_down(feature: &str) -> Result<(), Box<dyn std::error::Error>> {
     // 3. Get project root (parent of .force/)
     let project_root = force_dir.parent().ok_or("Invalid .force/ location")?;
 
-    // 4. Resolve worktree path
+    // 4. Resolve worktree path, preferring the one the session recorded
     let feature_slug = env::slugify(feature);
-    let worktree_path =
-        worktree::resolve_worktree_path(project_root, &feature_slug, &force_config.worktree.path);
+    let mut session = state::get_session(&force_dir, feature)?;
+    // The session may have been named by its slug
+    let feature = &session
+        .as_ref()
+        .map_or_else(|| feature.to_string(), |s| s.feature.clone());
+    let worktree_path = match &session {
+        Some(session) => session.worktree.clone(),
+        None => worktree::resolve_worktree_path(
+            project_root,
+            &feature_slug,
+            &force_config.worktree.path,
+        ),
+    };
+    let delete_branch = delete_branch.unwrap_or(force_config.worktree.delete_branch);
+    // Only branches Force created are ever deleted
+    let created_branch = session.as_ref().is_some_and(|s| s.created_branch);
+    let deletes_branch = created_branch && delete_branch != config::DeleteBranch::Never;
+    // Adopted worktrees and their branches belong to whoever made them
+    let adopted = session.as_ref().is_some_and(|s| s.adopted);
+    let remove_worktree = force_config.worktree.remove_on_down && !adopted;
 
-    // 5. Generate environment
-    let force_env = env::ForceEnv::new(feature, &force_dir, worktree_path.clone());
+    // 5. Generate environment, as the session's scripts received it
+    let force_env = match &session {
+        Some(session) => env::ForceEnv::for_session(session, &force_dir),
+        None => env::ForceEnv::new(
+            feature,
+            &force_dir,
+            worktree_path.clone(),
+            env::hash_to_offset(feature, force_config.ports.range),
+            &force_config.ports,
+        )?,
+    }
+    .with_vars(&force_config.env)?;
     println!(
         "Feature: {} (slug: {})",
         force_env.feature, force_env.feature_slug
     );
 
     // 6. Discover and load scripts
-    let scripts = config::load_scripts(&force_dir)?;
+    let scripts = config::load_scripts(&force_dir, &force_config)?;
     println!("Found {} script(s)", scripts.len());
 
-    // 7. Execute down scripts in reverse order (if worktree exists)
+    // 7. Refuse to remove a worktree holding work that would be lost
+    if remove_worktree && worktree_path.exists() {
+        // Seeded files only stand aside while nobody has touched them
+        let force_paths: Vec<&Path> = session
+            .iter()
+            .flat_map(|s| {
+                s.files
+                    .iter()
+                    .chain(s.seeded.iter().filter(|f| files::is_unchanged(f)))
+            })
+            .map(|f| f.path.as_path())
+            .collect();
+        let keeps_branch = !created_branch || delete_branch != config::DeleteBranch::Always;
+        save_unsaved_work(
+            &worktree_path,
+            feature,
+            &force_env.feature_slug,
+            &force_paths,
+            on_unsaved,
+            keeps_branch,
+        )?;
+    }
+
+    // 8. Mark session as going down so a failed teardown stays visible
+    if let Some(session) = &mut session {
+        session.status = state::SessionStatus::Down;
+        session.owner = Some(service::this_run());
+        state::save_session(&force_dir, session)?;
+    }
+
+    // 9. Stop background services before their down scripts run
+    if let Some(session) = &mut session {
+        runner::stop_services(&session.services)?;
+        session.services.clear();
+        state::save_session(&force_dir, session)?;
+    }
+
+    // 10. Execute down scripts in reverse order (if worktree exists), then
+    // remove generated files that weren't edited
     if worktree_path.exists() {
         runner::run_down(&scripts, &force_env)?;
+        if let Some(session) = &session {
+            files::remove_files(&session.files)?;
+        }
     } else {
         println!("Worktree not found, skipping down scripts");
     }
 
-    // 8. Remove worktree if configured
-    if force_config.worktree.remove_on_down {
+    // 11. Remove worktree if configured
+    let branch = match &session {
+        Some(session) => session.branch.clone(),
+        None => worktree::expand_template(&force_config.worktree.branch, &feature_slug),
+    };
+    if adopted {
+        println!("Keeping adopted worktree at: {}", worktree_path.display());
+    } else if remove_worktree {
         worktree::remove_worktree(project_root, &worktree_path)?;
         println!("Removed worktree at: {}", worktree_path.display());
+
+        // 12. Delete the branch if configured
+        if deletes_branch {
+            let base = session.as_ref().and_then(|s| s.base.as_deref());
+            remove_branch(
+                project_root,
+                &branch,
+                base,
+                &force_config.worktree,
+                delete_branch,
+            )?;
+        } else if delete_branch != config::DeleteBranch::Never {
+            println!("Kept branch '{}' (Force didn't create it)", branch);
+        }
+    } else if delete_branch != config::DeleteBranch::Never {
+        // The kept worktree still has the branch checked out
+        println!(
+            "Kept branch '{}' (the worktree is kept, since remove_on_down = false)",
+            branch
+        );
     }
 
-    // 9. Unregister session
+    // 13. Unregister session
     state::remove_session(&force_dir, feature)?;
 
     println!("\nSession '{}' torn down.", feature);
     Ok(())
 }
 
-fn run_ls() -> Result<(), Box<dyn std::error::Error>> {
+/// Delete a torn-down session's branch, which Force created, as
+/// `delete_branch` says. `merged` checks against the `base` the branch
+/// started from, or the configured base for sessions that didn't record one.
+fn remove_branch(
+    project_root: &Path,
+    branch: &str,
+    base: Option<&str>,
+    worktree_config: &config::WorktreeConfig,
+    delete_branch: config::DeleteBranch,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !worktree::branch_exists(project_root, branch)? {
+        return Ok(());
+    }
+
+    if delete_branch == config::DeleteBranch::Merged {
+        let base = base.or(worktree_config.base.as_deref()).unwrap_or("HEAD");
+        if !worktree::is_merged(project_root, branch, base)? {
+            println!("Kept branch '{}' (not fully merged into {})", branch, base);
+            return Ok(());
+        }
+    }
+
+    worktree::delete_branch(project_root, branch)?;
+    println!("Deleted branch '{}'", branch);
+    Ok(())
+}
+
+/// Deal with uncommitted changes and unpushed commits in a worktree about to
+/// be removed, failing if they would be lost. `force_paths` are files Force
+/// generated or seeded, which don't count as work. `keeps_branch` says whether
+/// the worktree's branch survives, holding its commits. A backup is named
+/// after the session's recorded `slug`.
+fn save_unsaved_work(
+    worktree_path: &Path,
+    feature: &str,
+    slug: &str,
+    force_paths: &[&Path],
+    on_unsaved: OnUnsaved,
+    keeps_branch: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if on_unsaved == OnUnsaved::Discard {
+        return Ok(());
+    }
+    let work = worktree::unsaved_work(worktree_path, force_paths)?;
+    if work.is_empty() {
+        return Ok(());
+    }
+
+    match on_unsaved {
+        OnUnsaved::Refuse => {
+            return Err(format!(
+                "Worktree at {} has {}.\nCommit and push it, or run `force down {}` with --stash or --backup to keep it, or --force to discard it.",
+                worktree_path.display(),
+                work,
+                feature
+            )
+            .into());
+        }
+        OnUnsaved::Stash => {
+            if work.commits > 0 && !keeps_branch {
+                return Err(format!(
+                    "{} commit(s) not on any other branch would be deleted with the branch, and --stash only keeps uncommitted changes. Use --backup instead.",
+                    work.commits
+                )
+                .into());
+            }
+            if !work.changes.is_empty() {
+                let message = format!("force down {}", feature);
+                worktree::stash_changes(worktree_path, &work.changes, &message)?;
+                println!(
+                    "Stashed {} change(s) as '{}' (restore with `git stash pop`)",
+                    work.changes.len(),
+                    message
+                );
+            }
+            if work.commits > 0 {
+                println!(
+                    "{} commit(s) not on any other branch stay on the worktree's branch",
+                    work.commits
+                );
+            }
+        }
+        OnUnsaved::Backup => {
+            let refname = worktree::backup_worktree(worktree_path, slug, force_paths)?;
+            println!("Saved {} to {}", work, refname);
+        }
+        OnUnsaved::Discard => {}
+    }
+    Ok(())
+}
+
+fn run_rename(old: &str, new: &str, new_port: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // 1. Find .force/ directory and load configuration
+    let force_dir = config::find_force_dir()?;
+    let force_config = config::load_config(&force_dir)?;
+    let project_root = force_dir.parent().ok_or("Invalid .force/ location")?;
+
+    // 2. Check the old session can be renamed to the new name. The state
+    // stays locked until the session is recorded under it
+    let state_lock = state::lock(&force_dir)?;
+    let session =
+        state::get_session(&force_dir, old)?.ok_or_else(|| format!("No session for '{}'", old))?;
+    let old = session.feature.as_str();
+    if old == new {
+        return Err(format!("Session '{}' already has that name", old).into());
+    }
+    if state::get_session(&force_dir, new)?.is_some_and(|s| s.feature == new) {
+        return Err(format!("Session '{}' already exists", new).into());
+    }
+    let new_slug = env::feature_slug(new)?;
+    check_slug_available(&force_dir, new, &new_slug, Some(old))?;
+    if matches!(
+        session.status,
+        state::SessionStatus::Pending | state::SessionStatus::Down
+    ) {
+        return Err(format!(
+            "Session '{}' is {}; finish `force up` or `force down` first",
+            old, session.status
+        )
+        .into());
+    }
+    if !session.worktree.is_dir() {
+        return Err(format!(
+            "Worktree for '{}' not found at {}",
+            old,
+            session.worktree.display()
+        )
+        .into());
+    }
+    let scripts = config::load_scripts(&force_dir, &force_config)?;
+
+    // 3. Work out the new worktree path and branch. Adopted worktrees stay
+    // where they are, and only branches named from the template are renamed
+    let (new_path, new_branch) = if session.adopted {
+        (session.worktree.clone(), session.branch.clone())
+    } else {
+        let template = &force_config.worktree.branch;
+        let branch = if session.branch == worktree::expand_template(template, &session.slug) {
+            worktree::expand_template(template, &new_slug)
+        } else {
+            session.branch.clone()
+        };
+        let path =
+            worktree::resolve_worktree_path(project_root, &new_slug, &force_config.worktree.path);
+        (path, branch)
+    };
+    let moves = !worktree::same_path(&new_path, &session.worktree);
+    if moves && new_path.exists() {
+        return Err(format!("{} already exists", new_path.display()).into());
+    }
+    let renames_branch = new_branch != session.branch;
+    if renames_branch && worktree::branch_exists(project_root, &new_branch)? {
+        return Err(format!("Branch '{}' already exists", new_branch).into());
+    }
+
+    // 4. Keep the port offset unless asked for a new one
+    let port_offset = if new_port {
+        assign_port_offset(&force_dir, new, &force_config.ports)?
+    } else {
+        session.port_offset
+    };
+    let old_env = env::ForceEnv::for_session(&session, &force_dir).with_vars(&force_config.env)?;
+    let new_env = env::ForceEnv::new(
+        new,
+        &force_dir,
+        new_path.clone(),
+        port_offset,
+        &force_config.ports,
+    )?
+    .with_vars(&force_config.env)?;
+
+    // 5. Stop services, which run with the old values in the old location
+    runner::stop_services(&session.services)?;
+
+    // 6. Move the worktree and rename the branch. From here on a failure
+    // says what was already done, since none of it is undone
+    let mut applied: Vec<String> = Vec::new();
+    let partial =
+        |e: Box<dyn std::error::Error>, applied: &[String]| -> Box<dyn std::error::Error> {
+            if applied.is_empty() {
+                return e;
+            }
+            format!(
+                "{}\nRename of '{}' to '{}' stopped partway. Already done: {}.",
+                e,
+                old,
+                new,
+                applied.join("; ")
+            )
+            .into()
+        };
+    if moves {
+        worktree::move_worktree(project_root, &session.worktree, &new_path)?;
+        println!("Moved worktree to: {}", new_path.display());
+        applied.push(format!("moved the worktree to {}", new_path.display()));
+    }
+    if renames_branch {
+        worktree::rename_branch(project_root, &session.branch, &new_branch)
+            .map_err(|e| partial(e, &applied))?;
+        println!("Renamed branch '{}' to '{}'", session.branch, new_branch);
+        applied.push(format!(
+            "renamed branch '{}' to '{}'",
+            session.branch, new_branch
+        ));
+    }
+
+    // 7. Record the session under its new name, keeping its generated and
+    // seeded files so `force down` still knows about them
+    let moved = |files: &[state::GeneratedFile]| -> Vec<state::GeneratedFile> {
+        files
+            .iter()
+            .map(|file| state::GeneratedFile {
+                path: match file.path.strip_prefix(&session.worktree) {
+                    Ok(relative) => new_path.join(relative),
+                    Err(_) => file.path.clone(),
+                },
+                hash: file.hash.clone(),
+            })
+            .collect()
+    };
+    let moved_files = moved(&session.files);
+    let mut renamed = state::Session {
+        created_at: session.created_at,
+        last_up_at: session.last_up_at,
+        status: session.status,
+        files: moved_files.clone(),
+        seeded: moved(&session.seeded),
+        adopted: session.adopted,
+        created_branch: session.created_branch,
+        base: session.base.clone(),
+        ..state::Session::new(&new_env, &new_branch)
+    };
+    state::rename_session(&force_dir, old, &renamed).map_err(|e| partial(e, &applied))?;
+    drop(state_lock);
+    applied.push(format!("recorded the session as '{}'", new));
+
+    // 8. Run [rename] hooks with the old values as FORCE_OLD_*
+    runner::run_rename(&scripts, &new_env.clone().with_old(&old_env))
+        .map_err(|e| partial(e, &applied))?;
+
+    // 9. Re-render generated files with the new values, removing ones whose
+    // path changed
+    let templates = file_templates(&force_config, &scripts);
+    renamed.files =
+        files::write_files(&templates, &new_env, &moved_files).map_err(|e| partial(e, &applied))?;
+    for file in &moved_files {
+        if !renamed.files.iter().any(|f| f.path == file.path) && files::remove_file(file)? {
+            println!("Removed {}", file.path.display());
+        }
+    }
+    state::save_session(&force_dir, &renamed)?;
+
+    if !session.services.is_empty() {
+        println!(
+            "\nServices were stopped; run `force up {}` to start them again",
+            new
+        );
+    }
+    println!("\nSession '{}' renamed to '{}'.", old, new);
+    Ok(())
+}
+
+fn run_env(feature: &str, format: env::EnvFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let force_dir = config::find_force_dir()?;
+    let force_config = config::load_config(&force_dir)?;
+    let force_env = session_env(&force_dir, &force_config, feature)?;
+
+    print!("{}", env::render(&force_env.to_env_vars(), format));
+    Ok(())
+}
+
+fn run_exec(feature: &str, command: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let force_dir = config::find_force_dir()?;
+    let force_config = config::load_config(&force_dir)?;
+    let force_env = session_env(&force_dir, &force_config, feature)?;
+
+    let (program, args) = command.split_first().ok_or("No command given")?;
+    run_in_session(
+        runner::session_command(program, &force_env).args(args),
+        program,
+    )
+}
+
+fn run_shell(feature: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let force_dir = config::find_force_dir()?;
+    let force_config = config::load_config(&force_dir)?;
+    let force_env = session_env(&force_dir, &force_config, feature)?;
+
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    println!(
+        "Starting {} in {} (exit to leave session '{}')",
+        shell,
+        force_env.worktree.display(),
+        feature
+    );
+    run_in_session(&mut runner::session_command(&shell, &force_env), &shell)
+}
+
+/// Run a command interactively and exit with its exit code if it fails
+fn run_in_session(
+    command: &mut process::Command,
+    program: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let status = command
+        .status()
+        .map_err(|e| format!("Failed to run '{}': {}", program, e))?;
+    if !status.success() {
+        process::exit(runner::exit_code(status));
+    }
+    Ok(())
+}
+
+/// Refuse a feature name whose slug already belongs to another session, since
+/// the two would share a worktree, branch and database. `except` is a session
+/// allowed to hold the slug, such as the one being renamed.
+fn check_slug_available(
+    force_dir: &Path,
+    feature: &str,
+    slug: &str,
+    except: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(owner) = state::slug_owner(force_dir, slug)?
+        && owner.feature != feature
+        && Some(owner.feature.as_str()) != except
+    {
+        return Err(format!(
+            "'{}' has the slug '{}', which session '{}' already uses. \
+             Use `{}` or choose a different name.",
+            feature, slug, owner.feature, owner.feature
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Rebuild the environment scripts received for an active session
+fn session_env(
+    force_dir: &Path,
+    force_config: &config::ForceConfig,
+    feature: &str,
+) -> Result<env::ForceEnv, Box<dyn std::error::Error>> {
+    let session = state::get_session(force_dir, feature)?.ok_or_else(|| {
+        format!(
+            "No session for '{}'. Run 'force up {}' first.",
+            feature, feature
+        )
+    })?;
+
+    if !session.worktree.is_dir() {
+        return Err(format!(
+            "Worktree for '{}' not found at {}",
+            feature,
+            session.worktree.display()
+        )
+        .into());
+    }
+
+    Ok(env::ForceEnv::for_session(&session, force_dir).with_vars(&force_config.env)?)
+}
+
+fn run_ls(check: bool) -> Result<(), Box<dyn std::error::Error>> {
     let force_dir = config::find_force_dir()?;
     let force_config = config::load_config(&force_dir)?;
     let sessions = state::list_sessions(&force_dir)?;
 
     if sessions.is_empty() {
         println!("No active sessions");
-        return Ok(());
+    } else {
+        print_sessions(&sessions);
     }
 
-    let project_root = force_dir.parent().ok_or("Invalid .force/ location")?;
+    if check {
+        let project_root = force_dir.parent().ok_or("Invalid .force/ location")?;
+        let problems = prune::check(&force_dir, project_root, &force_config)?;
+        if problems.is_empty() {
+            println!("\nNo problems found");
+        } else {
+            println!("\nProblems (run `force prune` to clean up):");
+            for problem in problems {
+                println!("  {}", problem);
+            }
+        }
+    }
+
+    Ok(())
+}
 
+/// Ports are the ones recorded at `force up`, like the environment
+/// `force env` and `force exec` use
+fn print_sessions(sessions: &[state::Session]) {
     println!("Active sessions:");
-    for name in sessions {
-        let feature_slug = env::slugify(&name);
-        let worktree_path = worktree::resolve_worktree_path(
-            project_root,
-            &feature_slug,
-            &force_config.worktree.path,
-        );
-        let force_env = env::ForceEnv::new(&name, &force_dir, worktree_path);
-        println!("  {}  port {}", name, force_env.port);
+    for session in sessions {
+        let mut line = format!("  {}  port {}", session.feature, session.port);
+        for (name, port) in &session.ports {
+            line.push_str(&format!("  {} {}", name, port));
+        }
+        if session.status != state::SessionStatus::Up {
+            line.push_str(&format!("  ({})", session.status));
+        }
+        if session.adopted {
+            line.push_str(&format!("  adopted {}", session.worktree.display()));
+        }
+        println!("{}", line);
+    }
+}
+
+fn run_prune(yes: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let force_dir = config::find_force_dir()?;
+    let force_config = config::load_config(&force_dir)?;
+    let project_root = force_dir.parent().ok_or("Invalid .force/ location")?;
+
+    let problems = prune::check(&force_dir, project_root, &force_config)?;
+    if problems.is_empty() {
+        println!("Nothing to prune");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for problem in problems {
+        println!("\n{}", problem);
+        if !yes && !confirm(problem.action())? {
+            println!("Skipped");
+            continue;
+        }
+        if let Err(e) = clean_up(problem, &force_dir, project_root, &force_config) {
+            eprintln!("Error: {}", e);
+            failed += 1;
+        }
+    }
+
+    // Drop git's records of worktree directories that no longer exist
+    worktree::prune_worktrees(project_root)?;
+
+    if failed > 0 {
+        return Err(format!("{} problem(s) could not be cleaned up", failed).into());
+    }
+    Ok(())
+}
+
+/// Fix one problem found by `prune::check`
+fn clean_up(
+    problem: prune::Problem,
+    force_dir: &Path,
+    project_root: &Path,
+    force_config: &config::ForceConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match problem {
+        prune::Problem::MissingWorktree(session) => {
+            runner::stop_services(&session.services)?;
+            state::remove_session(force_dir, &session.feature)?;
+            println!("Forgot session '{}'", session.feature);
+        }
+        prune::Problem::OrphanedWorktree { path, slug } => {
+            // The original feature name is unknown, so the slug stands in for it
+            let force_env = env::ForceEnv::new(
+                &slug,
+                force_dir,
+                path.clone(),
+                env::hash_to_offset(&slug, force_config.ports.range),
+                &force_config.ports,
+            )?
+            .with_vars(&force_config.env)?;
+            save_unsaved_work(&path, &slug, &slug, &[], OnUnsaved::Refuse, true)?;
+            let scripts = config::load_scripts(force_dir, force_config)?;
+            runner::run_down(&scripts, &force_env)?;
+            worktree::remove_worktree(project_root, &path)?;
+            println!("Removed worktree at: {}", path.display());
+        }
+        prune::Problem::Interrupted(session) => {
+            run_down(&session.feature, OnUnsaved::Refuse, None)?
+        }
+        prune::Problem::DeadServices(mut session, dead) => {
+            session.services.retain(|s| !dead.contains(s));
+            state::save_session(force_dir, &session)?;
+            println!("Forgot stopped services of '{}'", session.feature);
+        }
     }
     Ok(())
 }
+
+/// Ask a yes/no question on stdin, defaulting to no
+fn confirm(question: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Undo a failed `force up`: stop services it started, run the down commands
+/// of completed scripts in reverse order, remove generated files, then remove
+/// the worktree if this run created it. Failures are reported but don't stop
+/// the remaining steps.
+fn rollback(
+    project_root: &Path,
+    completed: &[&config::LoadedScript],
+    services: &[state::ServiceProcess],
+    generated: &[state::GeneratedFile],
+    force_env: &env::ForceEnv,
+    remove_worktree: bool,
+    created_branch: Option<&str>,
+) {
+    let mut summary = Vec::new();
+
+    match runner::stop_services(services) {
+        Ok(()) => summary.extend(
+            services
+                .iter()
+                .map(|s| format!("  stopped      service {} (pid {})", s.name, s.pid)),
+        ),
+        Err(e) => summary.push(format!("  FAILED       stopping services: {}", e)),
+    }
+
+    for script in completed.iter().rev() {
+        let label = format!("{}/{}", script.script.meta.category, script.name);
+        if script.script.down.is_none() {
+            summary.push(format!("  skipped      {} (no down script)", label));
+            continue;
+        }
+        match runner::run_down_script(script, force_env) {
+            Ok(()) => summary.push(format!("  rolled back  {}", label)),
+            Err(e) => summary.push(format!("  FAILED       {}: {}", label, e)),
+        }
+    }
+
+    for file in generated {
+        match files::remove_file(file) {
+            Ok(true) => summary.push(format!("  removed      file {}", file.path.display())),
+            Ok(false) => {}
+            Err(e) => summary.push(format!("  FAILED       {}", e)),
+        }
+    }
+
+    if remove_worktree {
+        match worktree::remove_worktree(project_root, &force_env.worktree) {
+            Ok(()) => summary.push(format!(
+                "  removed      worktree {}",
+                force_env.worktree.display()
+            )),
+            Err(e) => summary.push(format!("  FAILED       worktree removal: {}", e)),
+        }
+    }
+
+    // A branch made for this run would otherwise be picked up, start point
+    // and all, by the next `force up`
+    if let Some(branch) = created_branch {
+        match worktree::delete_branch(project_root, branch) {
+            Ok(()) => summary.push(format!("  deleted      branch {}", branch)),
+            Err(e) => summary.push(format!("  FAILED       branch deletion: {}", e)),
+        }
+    }
+
+    println!("\nRollback summary:");
+    if summary.is_empty() {
+        println!("  nothing to roll back");
+    }
+    for line in summary {
+        println!("{}", line);
+    }
+}
+
+/// Reuse the session's port offset, or allocate one that doesn't collide
+/// with other active sessions or ports already bound on localhost
+fn assign_port_offset(
+    force_dir: &Path,
+    feature: &str,
+    ports_config: &config::PortsConfig,
+) -> Result<u16, Box<dyn std::error::Error>> {
+    let sessions = state::list_sessions(force_dir)?;
+    if let Some(session) = sessions.iter().find(|s| s.feature == feature) {
+        // Checked here, before any worktree exists, since the offset may
+        // have been recorded under a different [ports] config
+        ports_config.session_ports(session.port_offset)?;
+        return Ok(session.port_offset);
+    }
+
+    let taken: HashSet<u16> = sessions.iter().map(|s| s.port_offset).collect();
+    let port_offset = ports::allocate_offset(feature, &taken, ports_config)?;
+
+    let preferred = env::hash_to_offset(feature, ports_config.range);
+    if port_offset != preferred {
+        println!(
+            "Port {} is taken, using {} instead",
+            ports_config.base + preferred,
+            ports_config.base + port_offset
+        );
+    }
+
+    Ok(port_offset)
+}
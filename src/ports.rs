@@ -0,0 +1,126 @@
+use crate::config::PortsConfig;
+use crate::env::hash_to_offset;
+use std::collections::HashSet;
+use std::net::TcpListener;
+
+/// Pick a port offset for a new session.
+///
+/// Starts at the feature's hashed offset and probes forward (wrapping around)
+/// until it finds an offset that no other session has claimed and whose ports
+/// (the base port and every named port, plus the offset) can actually be
+/// bound on localhost.
+pub fn allocate_offset(
+    feature: &str,
+    taken: &HashSet<u16>,
+    ports: &PortsConfig,
+) -> Result<u16, Box<dyn std::error::Error>> {
+    let range = ports.range;
+    let start = hash_to_offset(feature, range);
+    let bases: Vec<u16> = std::iter::once(ports.base)
+        .chain(ports.named.values().copied())
+        .collect();
+
+    for step in 0..range {
+        let offset = ((start as u32 + step as u32) % range as u32) as u16;
+        if taken.contains(&offset) {
+            continue;
+        }
+        if !bases.iter().all(|base| is_port_available(base + offset)) {
+            continue;
+        }
+        return Ok(offset);
+    }
+
+    Err(format!(
+        "No free port available in {}-{}",
+        ports.base,
+        ports.base + (range - 1)
+    )
+    .into())
+}
+
+/// Check whether a port can be bound on localhost
+pub fn is_port_available(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_ports() -> PortsConfig {
+        PortsConfig::default()
+    }
+
+    #[test]
+    fn test_allocate_offset_prefers_hash() {
+        let ports = default_ports();
+        let preferred = hash_to_offset("alloc-prefers-hash", ports.range);
+        if !is_port_available(ports.base + preferred) {
+            return; // Something on this machine already holds the port
+        }
+        let offset = allocate_offset("alloc-prefers-hash", &HashSet::new(), &ports).unwrap();
+        assert_eq!(offset, preferred);
+    }
+
+    #[test]
+    fn test_allocate_offset_skips_taken() {
+        let ports = default_ports();
+        let preferred = hash_to_offset("alloc-skips-taken", ports.range);
+        let taken: HashSet<u16> = [preferred].into_iter().collect();
+        let offset = allocate_offset("alloc-skips-taken", &taken, &ports).unwrap();
+        assert_ne!(offset, preferred);
+        assert!(offset < ports.range);
+    }
+
+    #[test]
+    fn test_allocate_offset_skips_bound_port() {
+        let ports = default_ports();
+        let preferred = hash_to_offset("alloc-skips-bound", ports.range);
+        let Ok(_listener) = TcpListener::bind(("127.0.0.1", ports.base + preferred)) else {
+            return; // Already bound elsewhere, which is what we want to simulate
+        };
+        let offset = allocate_offset("alloc-skips-bound", &HashSet::new(), &ports).unwrap();
+        assert_ne!(offset, preferred);
+    }
+
+    #[test]
+    fn test_allocate_offset_checks_named_ports() {
+        let mut ports = default_ports();
+        ports.named.insert("api".to_string(), 5000);
+        let preferred = hash_to_offset("alloc-named-ports", ports.range);
+        let Ok(_listener) = TcpListener::bind(("127.0.0.1", 5000 + preferred)) else {
+            return;
+        };
+        let offset = allocate_offset("alloc-named-ports", &HashSet::new(), &ports).unwrap();
+        assert_ne!(offset, preferred);
+    }
+
+    #[test]
+    fn test_allocate_offset_uses_configured_range() {
+        let ports = PortsConfig {
+            base: 30000,
+            range: 10,
+            ..default_ports()
+        };
+        let taken: HashSet<u16> = (0..9).collect();
+        let offset = allocate_offset("alloc-configured-range", &taken, &ports).unwrap();
+        assert_eq!(offset, 9);
+    }
+
+    #[test]
+    fn test_allocate_offset_wraps_around() {
+        let ports = default_ports();
+        let preferred = hash_to_offset("alloc-wraps", ports.range);
+        let taken: HashSet<u16> = (preferred..ports.range).collect();
+        let offset = allocate_offset("alloc-wraps", &taken, &ports).unwrap();
+        assert!(offset < preferred);
+    }
+
+    #[test]
+    fn test_allocate_offset_fails_when_exhausted() {
+        let ports = default_ports();
+        let taken: HashSet<u16> = (0..ports.range).collect();
+        assert!(allocate_offset("alloc-exhausted", &taken, &ports).is_err());
+    }
+}
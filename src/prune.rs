@@ -0,0 +1,144 @@
+use crate::config::ForceConfig;
+use crate::service;
+use crate::state::{self, ServiceProcess, Session, SessionStatus};
+use crate::worktree::{self, WorktreeEntry};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A mismatch between Force's session state and the repository's worktrees
+pub enum Problem {
+    /// The session's worktree directory no longer exists
+    MissingWorktree(Session),
+    /// A worktree at a Force worktree path that no session owns
+    OrphanedWorktree { path: PathBuf, slug: String },
+    /// A `force up` or `force down` that didn't finish
+    Interrupted(Session),
+    /// Recorded services that are no longer running
+    DeadServices(Session, Vec<ServiceProcess>),
+}
+
+impl Problem {
+    /// What cleaning up this problem will do, phrased as a question
+    pub fn action(&self) -> &'static str {
+        match self {
+            Problem::MissingWorktree(_) => "Stop its services and forget the session?",
+            Problem::OrphanedWorktree { .. } => "Run down scripts and remove the worktree?",
+            Problem::Interrupted(_) => "Tear the session down?",
+            Problem::DeadServices(..) => "Forget the stopped services?",
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingWorktree(session) => write!(
+                f,
+                "{}: worktree {} is missing",
+                session.feature,
+                session.worktree.display()
+            ),
+            Problem::OrphanedWorktree { path, .. } => {
+                write!(f, "worktree {} has no session", path.display())
+            }
+            Problem::Interrupted(session) => {
+                let command = match session.status {
+                    SessionStatus::Down => "force down",
+                    _ => "force up",
+                };
+                write!(
+                    f,
+                    "{}: session is {}, `{}` didn't finish",
+                    session.feature, session.status, command
+                )
+            }
+            Problem::DeadServices(session, services) => {
+                let names: Vec<String> = services
+                    .iter()
+                    .map(|s| format!("'{}' (pid {})", s.name, s.pid))
+                    .collect();
+                write!(
+                    f,
+                    "{}: service {} is no longer running",
+                    session.feature,
+                    names.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Compare active sessions with `git worktree list`
+pub fn check(
+    force_dir: &Path,
+    project_root: &Path,
+    force_config: &ForceConfig,
+) -> Result<Vec<Problem>, Box<dyn std::error::Error>> {
+    let sessions = state::list_sessions(force_dir)?;
+    let worktrees = worktree::list_worktrees(project_root)?;
+    let mut problems = Vec::new();
+
+    for entry in &worktrees {
+        if sessions
+            .iter()
+            .any(|s| worktree::same_path(&s.worktree, &entry.path))
+        {
+            continue;
+        }
+        if let Some(slug) = force_slug(entry, project_root, force_config)
+            && !entry.prunable
+        {
+            problems.push(Problem::OrphanedWorktree {
+                path: entry.path.clone(),
+                slug,
+            });
+        }
+    }
+
+    for session in sessions {
+        if !session.worktree.exists() {
+            problems.push(Problem::MissingWorktree(session));
+        } else if matches!(session.status, SessionStatus::Pending | SessionStatus::Down) {
+            // Leave runs that are still going, e.g. in another terminal
+            if !session.owner.as_ref().is_some_and(service::is_run_alive) {
+                problems.push(Problem::Interrupted(session));
+            }
+        } else {
+            let dead: Vec<ServiceProcess> = session
+                .services
+                .iter()
+                .filter(|s| !service::is_service_running(s))
+                .cloned()
+                .collect();
+            if !dead.is_empty() {
+                problems.push(Problem::DeadServices(session, dead));
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
+/// The feature slug a worktree was created for, if it sits where Force would
+/// have put it
+fn force_slug(
+    entry: &WorktreeEntry,
+    project_root: &Path,
+    force_config: &ForceConfig,
+) -> Option<String> {
+    let file_name = entry
+        .path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(String::from);
+    let branch_slug = entry
+        .branch
+        .as_deref()
+        .and_then(|branch| worktree::slug_from_branch(branch, &force_config.worktree.branch));
+
+    [file_name, branch_slug].into_iter().flatten().find(|slug| {
+        let expected =
+            worktree::resolve_worktree_path(project_root, slug, &force_config.worktree.path);
+        worktree::same_path(&expected, &entry.path)
+    })
+}
@@ -0,0 +1,306 @@
+use crate::config::{self, ReadyCheck};
+use crate::env::{self, ForceEnv};
+use crate::runner;
+use crate::service;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::os::unix::process::CommandExt;
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Upper bound for a single connection attempt or HTTP request
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often a check command is polled for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A readiness check with its variables expanded
+enum Probe {
+    Tcp(String),
+    Http { address: String, path: String },
+    Command(String),
+}
+
+impl Probe {
+    fn new(check: &ReadyCheck, env: &ForceEnv) -> Result<Self, String> {
+        let vars = env.to_env_vars();
+        if let Some(tcp) = &check.tcp {
+            let target = env::expand(tcp, &vars)?;
+            // A bare port means localhost
+            let address = if target.parse::<u16>().is_ok() {
+                format!("localhost:{}", target)
+            } else {
+                target
+            };
+            return Ok(Probe::Tcp(address));
+        }
+        if let Some(http) = &check.http {
+            let url = env::expand(http, &vars)?;
+            let rest = url
+                .strip_prefix("http://")
+                .ok_or_else(|| format!("'{}' is not an http:// URL", url))?;
+            let (host, path) = match rest.find('/') {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, "/"),
+            };
+            let address = if host.contains(':') {
+                host.to_string()
+            } else {
+                format!("{}:80", host)
+            };
+            return Ok(Probe::Http {
+                address,
+                path: path.to_string(),
+            });
+        }
+        if let Some(command) = &check.command {
+            return Ok(Probe::Command(command.clone()));
+        }
+        Err("no check configured".to_string())
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Probe::Tcp(address) => format!("tcp {}", address),
+            Probe::Http { address, path } => format!("http://{}{}", address, path),
+            Probe::Command(command) => format!("`{}`", command),
+        }
+    }
+
+    /// Run the check once. A command still running at `deadline`, or after
+    /// ATTEMPT_TIMEOUT for an attempt started near it, is killed along with
+    /// everything it started.
+    fn attempt(&self, env: &ForceEnv, deadline: Instant) -> Result<(), String> {
+        match self {
+            Probe::Tcp(address) => connect(address).map(|_| ()),
+            Probe::Http { address, path } => http_get(address, path),
+            Probe::Command(command) => {
+                let mut child = runner::shell_command(command, env)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .process_group(0)
+                    .spawn()
+                    .map_err(|e| e.to_string())?;
+                let deadline = deadline.max(Instant::now() + ATTEMPT_TIMEOUT);
+                let status = loop {
+                    if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+                        break status;
+                    }
+                    if Instant::now() >= deadline {
+                        service::stop(child.id(), service::STOP_GRACE_PERIOD)
+                            .map_err(|e| e.to_string())?;
+                        child.wait().map_err(|e| e.to_string())?;
+                        return Err("still running at the timeout and was killed".to_string());
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                };
+                match status.code() {
+                    Some(0) => Ok(()),
+                    Some(code) => Err(format!("exited with code {}", code)),
+                    None => Err("killed by a signal".to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// Poll `check` until it passes or its timeout runs out.
+///
+/// `service_pid` is the background service being checked, if any; the wait
+/// ends early if it exits.
+pub fn wait(
+    check: &ReadyCheck,
+    env: &ForceEnv,
+    label: &str,
+    service_pid: Option<u32>,
+) -> Result<(), String> {
+    let probe = Probe::new(check, env)?;
+    println!(
+        "[{}] waiting for {} (timeout {})",
+        label,
+        probe.describe(),
+        config::format_duration(check.timeout)
+    );
+
+    let deadline = Instant::now() + check.timeout;
+    loop {
+        let last_error = match probe.attempt(env, deadline) {
+            Ok(()) => {
+                println!("[{}] ready", label);
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+
+        if service_pid.is_some_and(|pid| !service::is_running(pid)) {
+            return Err("service exited before becoming ready".to_string());
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(format!(
+                "{} still failing after {}: {}",
+                probe.describe(),
+                config::format_duration(check.timeout),
+                last_error
+            ));
+        }
+        thread::sleep(check.interval.min(deadline - now));
+    }
+}
+
+fn connect(address: &str) -> Result<TcpStream, String> {
+    let addrs: Vec<SocketAddr> = address
+        .to_socket_addrs()
+        .map_err(|e| format!("can't resolve {}: {}", address, e))?
+        .collect();
+
+    let mut last_error = format!("no addresses for {}", address);
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, ATTEMPT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+/// Send a minimal HTTP/1.0 GET and require a 2xx status
+fn http_get(address: &str, path: &str) -> Result<(), String> {
+    let mut stream = connect(address)?;
+    stream
+        .set_read_timeout(Some(ATTEMPT_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    // The status line fits well within the first read
+    let mut buffer = [0u8; 256];
+    let read = stream.read(&mut buffer).map_err(|e| e.to_string())?;
+    let response = String::from_utf8_lossy(&buffer[..read]);
+    let status = response
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or_else(|| "invalid HTTP response".to_string())?;
+
+    if status.starts_with('2') {
+        Ok(())
+    } else {
+        Err(format!("HTTP status {}", status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PortsConfig;
+    use std::net::TcpListener;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn test_env(dir: &Path) -> ForceEnv {
+        ForceEnv::new(
+            "ready-test",
+            &dir.join(".force"),
+            dir.to_path_buf(),
+            0,
+            &PortsConfig::default(),
+        )
+        .unwrap()
+    }
+
+    fn check(tcp: Option<&str>, http: Option<&str>, command: Option<&str>) -> ReadyCheck {
+        ReadyCheck {
+            tcp: tcp.map(String::from),
+            http: http.map(String::from),
+            command: command.map(String::from),
+            timeout: Duration::from_millis(500),
+            interval: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn test_tcp_check_passes_when_listening() {
+        let dir = TempDir::new().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let address = format!("127.0.0.1:{}", port);
+
+        let tcp = check(Some(&address), None, None);
+        assert!(wait(&tcp, &test_env(dir.path()), "t", None).is_ok());
+    }
+
+    #[test]
+    fn test_tcp_check_times_out() {
+        let dir = TempDir::new().unwrap();
+        // Bind and drop to find a port that is very likely closed
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let address = format!("127.0.0.1:{}", port);
+
+        let tcp = check(Some(&address), None, None);
+        let err = wait(&tcp, &test_env(dir.path()), "t", None).unwrap_err();
+        assert!(err.contains("still failing after"));
+    }
+
+    #[test]
+    fn test_http_check_requires_2xx() {
+        let dir = TempDir::new().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/health", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0u8; 512];
+                let _ = stream.read(&mut request);
+                let _ = write!(stream, "HTTP/1.0 {}\r\n\r\n", status);
+            }
+        });
+
+        let http = check(None, Some(&url), None);
+        wait(&http, &test_env(dir.path()), "t", None).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_command_check_uses_force_env() {
+        let dir = TempDir::new().unwrap();
+        let ok = check(None, None, Some("test \"$FORCE_FEATURE\" = ready-test"));
+        assert!(wait(&ok, &test_env(dir.path()), "t", None).is_ok());
+
+        let failing = check(None, None, Some("exit 3"));
+        let err = wait(&failing, &test_env(dir.path()), "t", None).unwrap_err();
+        assert!(err.contains("exited with code 3"));
+    }
+
+    #[test]
+    fn test_command_check_is_killed_at_timeout() {
+        let dir = TempDir::new().unwrap();
+        let hanging = check(None, None, Some("sleep 30"));
+        let started = Instant::now();
+        let err = wait(&hanging, &test_env(dir.path()), "t", None).unwrap_err();
+        assert!(err.contains("was killed"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_tcp_check_expands_variables() {
+        let dir = TempDir::new().unwrap();
+        let env = test_env(dir.path());
+        let probe = Probe::new(&check(Some("$FORCE_PORT"), None, None), &env).unwrap();
+        assert_eq!(probe.describe(), format!("tcp localhost:{}", env.port));
+    }
+}
@@ -1,76 +1,425 @@
-use crate::config::LoadedScript;
+use crate::config::{self, LoadedScript, ScriptCommand};
 use crate::env::ForceEnv;
-use std::process::Command;
+use crate::ready;
+use crate::service;
+use crate::state::{self, ServiceProcess};
+use std::any::Any;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-/// Run a script with the force environment
-pub fn run_script(script: &LoadedScript, env: &ForceEnv) -> Result<(), Box<dyn std::error::Error>> {
+/// How often to check whether a command with a timeout has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A script failure during `run_scripts`
+pub struct ScriptsFailure {
+    /// Indices of scripts that finished successfully, in completion order
+    pub completed: Vec<usize>,
+    /// Services started before the failure, which are still running
+    pub services: Vec<ServiceProcess>,
+    /// Name of the script that failed first
+    pub failed: String,
+    pub error: String,
+}
+
+/// Run up scripts, starting each one once everything it must follow is done.
+///
+/// A script waits for every earlier script, except earlier scripts in its own
+/// category with the same priority when `is_parallel` returns true for that
+/// category. Explicit
+/// `depends_on` entries are always waited for. At most `jobs` scripts run at
+/// once. After a failure no new scripts are started, running ones are allowed
+/// to finish, and the first failure is returned.
+///
+/// Returns the service scripts that were started in the background.
+pub fn run_scripts(
+    scripts: &[LoadedScript],
+    env: &ForceEnv,
+    jobs: usize,
+    is_parallel: impl Fn(&str) -> bool,
+) -> Result<Vec<ServiceProcess>, ScriptsFailure> {
+    let index_of = |name: &str| scripts.iter().position(|s| s.name == name);
+    let parallel: Vec<bool> = scripts
+        .iter()
+        .map(|s| jobs > 1 && is_parallel(&s.script.meta.category))
+        .collect();
+
+    // waits_for[i] holds the indices that must finish before script i starts
+    let waits_for: Vec<Vec<usize>> = scripts
+        .iter()
+        .enumerate()
+        .map(|(i, script)| {
+            let meta = &script.script.meta;
+            let mut waits: Vec<usize> = (0..i)
+                .filter(|&j| {
+                    let other = &scripts[j].script.meta;
+                    !(parallel[i]
+                        && other.category == meta.category
+                        && other.priority.unwrap_or(0) == meta.priority.unwrap_or(0))
+                })
+                .collect();
+            waits.extend(
+                script
+                    .script
+                    .meta
+                    .depends_on
+                    .iter()
+                    .filter_map(|d| index_of(d)),
+            );
+            waits
+        })
+        .collect();
+
+    let mut started = vec![false; scripts.len()];
+    let mut done = vec![false; scripts.len()];
+    let mut completed = Vec::new();
+    let mut services = Vec::new();
+    let mut failure: Option<(String, String)> = None;
+    let mut running = 0;
+
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+
+        loop {
+            if failure.is_none() {
+                for i in 0..scripts.len() {
+                    if running >= jobs.max(1) {
+                        break;
+                    }
+                    if started[i] || !waits_for[i].iter().all(|&j| done[j]) {
+                        continue;
+                    }
+                    started[i] = true;
+                    running += 1;
+                    let tx = tx.clone();
+                    let prefix_output = parallel[i];
+                    scope.spawn(move || {
+                        // A panic, e.g. from printing to a closed stdout, must
+                        // still be reported or the loop below would wait forever
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                            run_script(&scripts[i], env, prefix_output).map_err(|e| e.to_string())
+                        }))
+                        .unwrap_or_else(|payload| Err(panic_message(payload.as_ref())));
+                        let _ = tx.send((i, result));
+                    });
+                }
+            }
+
+            if running == 0 {
+                break;
+            }
+
+            let Ok((i, result)) = rx.recv() else {
+                // Every script thread is gone without reporting back
+                let name = (0..scripts.len())
+                    .find(|&i| started[i] && !done[i])
+                    .map_or_else(String::new, |i| scripts[i].name.clone());
+                failure.get_or_insert((name, "script stopped without a result".to_string()));
+                break;
+            };
+            running -= 1;
+            match result {
+                Ok(started) => {
+                    done[i] = true;
+                    completed.push(i);
+                    services.extend(started);
+                }
+                Err(e) => {
+                    if failure.is_none() {
+                        failure = Some((scripts[i].name.clone(), e));
+                    }
+                }
+            }
+        }
+    });
+
+    match failure {
+        Some((failed, error)) => Err(ScriptsFailure {
+            completed,
+            services,
+            failed,
+            error,
+        }),
+        None => Ok(services),
+    }
+}
+
+/// Error message for a script thread that panicked
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown error");
+    format!("script panicked: {}", message)
+}
+
+/// Run a script with the force environment.
+///
+/// With `prefix_output`, the script's stdout and stderr are captured and
+/// printed line by line prefixed with `[category/name]`, so output from
+/// scripts running at the same time stays readable.
+///
+/// Service scripts are started in the background with their output going to
+/// the session's log file, and the started process is returned.
+pub fn run_script(
+    script: &LoadedScript,
+    env: &ForceEnv,
+    prefix_output: bool,
+) -> Result<Option<ServiceProcess>, Box<dyn std::error::Error>> {
     let description = script
         .script
         .up
         .description
         .as_deref()
         .unwrap_or(&script.name);
+    let label = format!("{}/{}", script.script.meta.category, script.name);
 
-    println!(
-        "\n[{}/{}] {}",
-        script.script.meta.category, script.name, description
-    );
+    println!("\n[{}] {}", label, description);
+    let env = &env.clone().with_vars(&script.script.up.env)?;
+
+    if script.script.up.service {
+        let log_path = state::log_file(&env.force_dir, &env.feature_slug);
+        let pid = service::start(&script.script.up.run, env, &log_path, &label)?;
+        println!(
+            "[{}] started in background (pid {}), logging to {}",
+            label,
+            pid,
+            log_path.display()
+        );
+        if let Some(check) = &script.script.up.ready
+            && let Err(e) = ready::wait(check, env, &label, Some(pid))
+        {
+            service::stop(pid, service::STOP_GRACE_PERIOD)?;
+            return Err(format!("Script '{}' never became ready: {}", script.name, e).into());
+        }
+        return Ok(Some(ServiceProcess {
+            name: script.name.clone(),
+            pid,
+            started: service::start_time(pid),
+        }));
+    }
+
+    let what = format!("Script '{}'", script.name);
+    run_with_retries(&script.script.up, env, &label, &what, prefix_output)?;
+
+    if let Some(check) = &script.script.up.ready {
+        ready::wait(check, env, &label, None)
+            .map_err(|e| format!("Script '{}' never became ready: {}", script.name, e))?;
+    }
+
+    Ok(None)
+}
+
+/// How a single attempt at running a command ended
+enum Attempt {
+    Exited(ExitStatus),
+    TimedOut(ExitStatus),
+}
+
+/// Run a command, retrying it on failure or timeout as configured.
+///
+/// `what` names the command in errors, e.g. "Script 'database'".
+fn run_with_retries(
+    command: &ScriptCommand,
+    env: &ForceEnv,
+    label: &str,
+    what: &str,
+    prefix_output: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let attempts = command.retries + 1;
+
+    for attempt in 1..=attempts {
+        let mut error = match run_attempt(command, env, label, prefix_output)? {
+            Attempt::Exited(status) if status.success() => return Ok(()),
+            Attempt::Exited(status) => {
+                format!("{} failed with exit code {}", what, exit_code(status))
+            }
+            Attempt::TimedOut(status) => format!(
+                "{} timed out after {} and was killed (exit code {})",
+                what,
+                config::format_duration(command.timeout.unwrap_or_default()),
+                exit_code(status)
+            ),
+        };
+        if attempts > 1 {
+            error.push_str(&format!(" on attempt {} of {}", attempt, attempts));
+        }
+        if attempt == attempts {
+            return Err(error.into());
+        }
+
+        println!(
+            "[{}] {}, retrying in {}",
+            label,
+            error,
+            config::format_duration(command.retry_delay)
+        );
+        thread::sleep(command.retry_delay);
+    }
 
-    let status = Command::new("sh")
-        .arg("-c")
-        .arg(&script.script.up.run)
-        .envs(env.to_env_vars())
-        .current_dir(&env.worktree)
-        .status()?;
+    unreachable!("the last attempt always returns")
+}
 
-    if !status.success() {
-        let code = status.code().unwrap_or(-1);
-        return Err(format!("Script '{}' failed with exit code {}", script.name, code).into());
+/// Run a command once, killing its process group if it exceeds its timeout
+fn run_attempt(
+    command: &ScriptCommand,
+    env: &ForceEnv,
+    label: &str,
+    prefix_output: bool,
+) -> Result<Attempt, Box<dyn std::error::Error>> {
+    let mut process = shell_command(&command.run, env);
+    if command.timeout.is_some() {
+        // Its own process group lets a timeout kill everything it started
+        process.process_group(0);
+    }
+    if prefix_output {
+        process.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child = process.spawn()?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    thread::scope(|scope| {
+        if let Some(stdout) = stdout {
+            scope.spawn(|| forward_lines(stdout, label, false));
+        }
+        if let Some(stderr) = stderr {
+            scope.spawn(|| forward_lines(stderr, label, true));
+        }
+
+        let Some(timeout) = command.timeout else {
+            return Ok(Attempt::Exited(child.wait()?));
+        };
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Attempt::Exited(status));
+            }
+            if Instant::now() >= deadline {
+                service::stop(child.id(), service::STOP_GRACE_PERIOD)?;
+                return Ok(Attempt::TimedOut(child.wait()?));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    })
+}
+
+/// Build a command that runs in the session's worktree with its environment
+pub fn session_command(program: impl AsRef<OsStr>, env: &ForceEnv) -> Command {
+    let mut command = Command::new(program);
+    command.envs(env.to_env_vars()).current_dir(&env.worktree);
+    command
+}
+
+/// Build `sh -c <run>` in the session's context
+pub fn shell_command(run: &str, env: &ForceEnv) -> Command {
+    let mut command = session_command("sh", env);
+    command.arg("-c").arg(run);
+    command
+}
+
+/// Exit code as a shell would report it, with signals as 128 + signal number
+pub fn exit_code(status: ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(-1)
+}
+
+/// Stop background services, newest first. Services that already exited are
+/// skipped, so a reused PID is never signalled.
+pub fn stop_services(services: &[ServiceProcess]) -> Result<(), Box<dyn std::error::Error>> {
+    for process in services.iter().rev() {
+        if !service::is_service_running(process) {
+            println!(
+                "Service '{}' (pid {}) is no longer running",
+                process.name, process.pid
+            );
+            continue;
+        }
+        println!("Stopping service '{}' (pid {})", process.name, process.pid);
+        service::stop(process.pid, service::STOP_GRACE_PERIOD)?;
     }
 
     Ok(())
 }
 
+/// Print each line from a child's output stream prefixed with its label
+fn forward_lines(stream: impl Read, label: &str, to_stderr: bool) {
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        if to_stderr {
+            eprintln!("[{}] {}", label, line);
+        } else {
+            println!("[{}] {}", label, line);
+        }
+    }
+}
+
 /// Run down scripts in reverse order
 pub fn run_down(
     scripts: &[LoadedScript],
     env: &ForceEnv,
 ) -> Result<(), Box<dyn std::error::Error>> {
     for script in scripts.iter().rev() {
-        let down = match &script.script.down {
-            Some(d) => d,
-            None => {
-                println!(
-                    "\n[{}/{}] (no down script, skipping)",
-                    script.script.meta.category, script.name
-                );
-                continue;
-            }
-        };
+        run_down_script(script, env)?;
+    }
 
-        let description = down.description.as_deref().unwrap_or(&script.name);
+    Ok(())
+}
 
-        println!(
-            "\n[{}/{}] {}",
-            script.script.meta.category, script.name, description
-        );
+/// Run `[rename]` hooks in up order, silently skipping scripts without one
+pub fn run_rename(
+    scripts: &[LoadedScript],
+    env: &ForceEnv,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for script in scripts {
+        let Some(rename) = &script.script.rename else {
+            continue;
+        };
+        let label = format!("{}/{}", script.script.meta.category, script.name);
+        let description = rename.description.as_deref().unwrap_or(&script.name);
+        println!("\n[{}] {}", label, description);
 
-        let status = Command::new("sh")
-            .arg("-c")
-            .arg(&down.run)
-            .envs(env.to_env_vars())
-            .current_dir(&env.worktree)
-            .status()?;
-
-        if !status.success() {
-            let code = status.code().unwrap_or(-1);
-            return Err(format!(
-                "Script '{}' down failed with exit code {}",
-                script.name, code
-            )
-            .into());
-        }
+        let env = &env.clone().with_vars(&rename.env)?;
+        let what = format!("Script '{}' rename", script.name);
+        run_with_retries(rename, env, &label, &what, false)?;
     }
 
     Ok(())
 }
+
+/// Run a single script's down command, skipping scripts without one
+pub fn run_down_script(
+    script: &LoadedScript,
+    env: &ForceEnv,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let down = match &script.script.down {
+        Some(d) => d,
+        None => {
+            println!(
+                "\n[{}/{}] (no down script, skipping)",
+                script.script.meta.category, script.name
+            );
+            return Ok(());
+        }
+    };
+
+    let description = down.description.as_deref().unwrap_or(&script.name);
+
+    println!(
+        "\n[{}/{}] {}",
+        script.script.meta.category, script.name, description
+    );
+
+    let env = &env.clone().with_vars(&down.env)?;
+    let label = format!("{}/{}", script.script.meta.category, script.name);
+    let what = format!("Script '{}' down", script.name);
+    run_with_retries(down, env, &label, &what, false)
+}
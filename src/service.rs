@@ -0,0 +1,241 @@
+use crate::env::ForceEnv;
+use crate::runner;
+use crate::state::{RunOwner, ServiceProcess};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a service gets to exit after SIGTERM before it is sent SIGKILL
+pub const STOP_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How often to check whether a stopping service has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Start a long-running command detached in its own process group.
+///
+/// stdout and stderr are appended to `log_path`. Returns the PID, which is
+/// also the process group ID.
+pub fn start(
+    run: &str,
+    env: &ForceEnv,
+    log_path: &Path,
+    label: &str,
+) -> Result<u32, Box<dyn std::error::Error>> {
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)?;
+    writeln!(log, "==> [{}] starting", label)?;
+
+    let child = runner::shell_command(run, env)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .process_group(0)
+        .spawn()?;
+
+    Ok(child.id())
+}
+
+/// Stop a service's whole process group: SIGTERM, then SIGKILL if it is
+/// still running after `grace`
+pub fn stop(pid: u32, grace: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    if !is_running(pid) {
+        return Ok(());
+    }
+
+    signal_group(pid, "TERM")?;
+
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline {
+        if !is_running(pid) {
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    signal_group(pid, "KILL")?;
+    Ok(())
+}
+
+/// Whether any non-zombie process is left in the process group
+pub fn is_running(pgid: u32) -> bool {
+    let output = match Command::new("ps")
+        .args(["-A", "-o", "pgid=", "-o", "stat="])
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return false,
+    };
+
+    String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        let group = fields.next().and_then(|g| g.parse::<u32>().ok());
+        let stat = fields.next().unwrap_or("");
+        group == Some(pgid) && !stat.starts_with('Z')
+    })
+}
+
+/// When the process with `pid` started, as printed by `ps -o lstart=`
+pub fn start_time(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "lstart=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let started = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !started.is_empty()).then_some(started)
+}
+
+/// Whether a recorded service is still running. After a reboot or PID wrap
+/// the PID may belong to an unrelated process, which is told apart by its
+/// start time. Records without a start time can't be checked and count as
+/// stopped.
+pub fn is_service_running(process: &ServiceProcess) -> bool {
+    let Some(started) = &process.started else {
+        return false;
+    };
+    match start_time(process.pid) {
+        Some(current) => &current == started && is_running(process.pid),
+        // The leader exited, but its PID can't be reused while other
+        // processes are still in its group
+        None => is_running(process.pid),
+    }
+}
+
+/// Identify this process, to record it as the owner of a session
+pub fn this_run() -> RunOwner {
+    let pid = std::process::id();
+    RunOwner {
+        pid,
+        started: start_time(pid),
+    }
+}
+
+/// Whether the `force` run that owns a session is still working on it
+pub fn is_run_alive(owner: &RunOwner) -> bool {
+    match &owner.started {
+        Some(started) => start_time(owner.pid).as_ref() == Some(started),
+        None => false,
+    }
+}
+
+fn signal_group(pgid: u32, signal: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg("--")
+        .arg(format!("-{}", pgid))
+        .output()?;
+
+    // The group may have exited between the check and the signal
+    if !output.status.success() && is_running(pgid) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Failed to send SIG{} to process group {}: {}",
+            signal, pgid, stderr
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PortsConfig;
+    use tempfile::TempDir;
+
+    fn test_env(dir: &Path) -> ForceEnv {
+        ForceEnv::new(
+            "service-test",
+            &dir.join(".force"),
+            dir.to_path_buf(),
+            0,
+            &PortsConfig::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_start_and_stop_service() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("logs/service.log");
+
+        let pid = start(
+            "echo started; sleep 30",
+            &test_env(dir.path()),
+            &log_path,
+            "services/test",
+        )
+        .unwrap();
+        assert!(is_running(pid));
+
+        stop(pid, Duration::from_secs(5)).unwrap();
+        assert!(!is_running(pid));
+
+        let log = fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains("==> [services/test] starting"));
+    }
+
+    #[test]
+    fn test_stop_kills_service_ignoring_sigterm() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("service.log");
+
+        let pid = start(
+            "trap '' TERM; while true; do sleep 0.1; done",
+            &test_env(dir.path()),
+            &log_path,
+            "services/stubborn",
+        )
+        .unwrap();
+        // Give the shell time to install its trap
+        thread::sleep(Duration::from_millis(200));
+
+        stop(pid, Duration::from_millis(300)).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert!(!is_running(pid));
+    }
+
+    #[test]
+    fn test_is_service_running_checks_start_time() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("service.log");
+
+        let pid = start("sleep 30", &test_env(dir.path()), &log_path, "services/id").unwrap();
+        let process = |started: Option<&str>| ServiceProcess {
+            name: "id".to_string(),
+            pid,
+            started: started.map(String::from),
+        };
+        let started = start_time(pid).unwrap();
+
+        assert!(is_service_running(&process(Some(&started))));
+        // Same PID, but a different process
+        assert!(!is_service_running(&process(Some(
+            "Thu Jan  1 00:00:00 1970"
+        ))));
+        assert!(!is_service_running(&process(None)));
+
+        stop(pid, Duration::from_secs(5)).unwrap();
+        assert!(!is_service_running(&process(Some(&started))));
+    }
+
+    #[test]
+    fn test_stop_already_exited_service() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("service.log");
+
+        let pid = start("true", &test_env(dir.path()), &log_path, "services/quick").unwrap();
+        thread::sleep(Duration::from_millis(200));
+
+        assert!(stop(pid, Duration::from_millis(100)).is_ok());
+    }
+}
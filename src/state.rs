@@ -1,6 +1,157 @@
-use std::collections::HashSet;
-use std::fs;
+use crate::config::{self, DEFAULT_PORT_RANGE};
+use crate::env::{ForceEnv, hash_to_offset, legacy_slugify};
+use crate::worktree;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Version of the state file format written by this build
+const STATE_VERSION: u32 = 1;
+
+/// Lifecycle of a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStatus {
+    /// `force up` started but hasn't finished
+    Pending,
+    /// `force up` completed successfully
+    Up,
+    /// A script failed during `force up`
+    Failed,
+    /// `force down` started but hasn't finished
+    Down,
+}
+
+impl std::fmt::Display for SessionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SessionStatus::Pending => "pending",
+            SessionStatus::Up => "up",
+            SessionStatus::Failed => "failed",
+            SessionStatus::Down => "down",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A registered session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub feature: String,
+    pub slug: String,
+    /// FORCE_DB_NAME when the session was created
+    #[serde(default)]
+    pub db_name: Option<String>,
+    pub port_offset: u16,
+    pub port: u16,
+    /// Named ports from config.toml at the time of `force up`
+    #[serde(default)]
+    pub ports: BTreeMap<String, u16>,
+    pub worktree: PathBuf,
+    pub branch: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    /// Seconds since the Unix epoch of the last successful `force up`
+    pub last_up_at: Option<u64>,
+    pub status: SessionStatus,
+    /// Background services started by `force up`
+    #[serde(default)]
+    pub services: Vec<ServiceProcess>,
+    /// Files generated from [[files]] templates by `force up`
+    #[serde(default)]
+    pub files: Vec<GeneratedFile>,
+    /// Files and symlinks copied or linked from the main checkout by
+    /// `force up`
+    #[serde(default)]
+    pub seeded: Vec<GeneratedFile>,
+    /// The worktree was adopted rather than created by Force
+    #[serde(default)]
+    pub adopted: bool,
+    /// Force created the branch, so `delete_branch` may remove it
+    #[serde(default)]
+    pub created_branch: bool,
+    /// What Force started the branch from, checked by `delete_branch =
+    /// "merged"`
+    #[serde(default)]
+    pub base: Option<String>,
+    /// The `force up` or `force down` process working on a pending or down
+    /// session
+    #[serde(default)]
+    pub owner: Option<RunOwner>,
+}
+
+/// A running `force` process, identified by PID and start time like a
+/// service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunOwner {
+    pub pid: u32,
+    pub started: Option<String>,
+}
+
+/// A running service script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceProcess {
+    /// Script name (file name without .toml)
+    pub name: String,
+    /// Process ID, which is also the service's process group ID
+    pub pid: u32,
+    /// Start time reported by `ps`, to tell the service apart from a later
+    /// process that reuses its PID
+    #[serde(default)]
+    pub started: Option<String>,
+}
+
+/// A file written from a [[files]] template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    /// Hash of the content Force wrote, to detect later edits
+    pub hash: String,
+}
+
+impl Session {
+    /// Build a pending session record for a freshly generated environment
+    pub fn new(env: &ForceEnv, branch: &str) -> Self {
+        Self {
+            feature: env.feature.clone(),
+            slug: env.feature_slug.clone(),
+            db_name: Some(env.db_name.clone()),
+            port_offset: env.port_offset,
+            port: env.port,
+            ports: env.ports.iter().cloned().collect(),
+            worktree: env.worktree.clone(),
+            branch: branch.to_string(),
+            created_at: now(),
+            last_up_at: None,
+            status: SessionStatus::Pending,
+            services: Vec::new(),
+            files: Vec::new(),
+            seeded: Vec::new(),
+            adopted: false,
+            created_branch: false,
+            base: None,
+            owner: None,
+        }
+    }
+}
+
+/// On-disk layout of the state file
+#[derive(Debug, Serialize, Deserialize)]
+struct StateFile {
+    version: u32,
+    sessions: Vec<Session>,
+}
+
+/// Current time as seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
 
 /// Get the state directory for a project based on its .force/ path
 pub fn get_state_dir(force_dir: &Path) -> PathBuf {
@@ -25,18 +176,76 @@ fn simple_hash(s: &str) -> String {
     format!("{:016x}", hash)
 }
 
-/// Get the sessions file path
-fn sessions_file(force_dir: &Path) -> PathBuf {
-    get_state_dir(force_dir).join("sessions")
+/// Get the state file path
+fn state_file(force_dir: &Path) -> PathBuf {
+    get_state_dir(force_dir).join("state.json")
+}
+
+/// Get the log file that a session's services write to
+pub fn log_file(force_dir: &Path, feature_slug: &str) -> PathBuf {
+    get_state_dir(force_dir)
+        .join("logs")
+        .join(format!("{}.log", feature_slug))
+}
+
+/// Get the file locked while the state is read, changed and written
+fn lock_file(force_dir: &Path) -> PathBuf {
+    get_state_dir(force_dir).join("state.lock")
+}
+
+thread_local! {
+    /// How many `StateLock`s this thread holds; only the outermost one locks
+    /// the file, since a second lock on it would wait forever
+    static LOCK_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Exclusive lock on a project's state, released when dropped. Concurrent
+/// `force` runs take it around every change so none of them drops another's
+/// sessions. Locks nest within a thread.
+pub struct StateLock {
+    file: Option<File>,
+}
+
+/// Wait for and take the lock on the project's state
+pub fn lock(force_dir: &Path) -> Result<StateLock, Box<dyn std::error::Error>> {
+    if LOCK_DEPTH.get() > 0 {
+        LOCK_DEPTH.set(LOCK_DEPTH.get() + 1);
+        return Ok(StateLock { file: None });
+    }
+
+    fs::create_dir_all(get_state_dir(force_dir))?;
+    let path = lock_file(force_dir);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    file.lock()
+        .map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+    LOCK_DEPTH.set(1);
+    Ok(StateLock { file: Some(file) })
 }
 
-/// Add a session to the state
-pub fn add_session(force_dir: &Path, feature: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let state_dir = get_state_dir(force_dir);
-    fs::create_dir_all(&state_dir)?;
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        LOCK_DEPTH.set(LOCK_DEPTH.get() - 1);
+        if let Some(file) = &self.file {
+            let _ = file.unlock();
+        }
+    }
+}
+
+/// Get the plain-text sessions file written by older versions
+fn legacy_sessions_file(force_dir: &Path) -> PathBuf {
+    get_state_dir(force_dir).join("sessions")
+}
 
+/// Add a session to the state, replacing any existing record for the feature
+pub fn save_session(force_dir: &Path, session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = lock(force_dir)?;
     let mut sessions = load_sessions(force_dir)?;
-    sessions.insert(feature.to_string());
+    sessions.insert(session.feature.clone(), session.clone());
     save_sessions(force_dir, &sessions)?;
 
     Ok(())
@@ -44,6 +253,7 @@ pub fn add_session(force_dir: &Path, feature: &str) -> Result<(), Box<dyn std::e
 
 /// Remove a session from the state
 pub fn remove_session(force_dir: &Path, feature: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = lock(force_dir)?;
     let mut sessions = load_sessions(force_dir)?;
     sessions.remove(feature);
     save_sessions(force_dir, &sessions)?;
@@ -51,58 +261,206 @@ pub fn remove_session(force_dir: &Path, feature: &str) -> Result<(), Box<dyn std
     Ok(())
 }
 
-/// List all sessions for a project
-pub fn list_sessions(force_dir: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+/// Replace the session recorded for `old_feature` with `session`
+pub fn rename_session(
+    force_dir: &Path,
+    old_feature: &str,
+    session: &Session,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = lock(force_dir)?;
+    let mut sessions = load_sessions(force_dir)?;
+    sessions.remove(old_feature);
+    sessions.insert(session.feature.clone(), session.clone());
+    save_sessions(force_dir, &sessions)?;
+
+    Ok(())
+}
+
+/// List all sessions for a project, sorted by feature name
+pub fn list_sessions(force_dir: &Path) -> Result<Vec<Session>, Box<dyn std::error::Error>> {
+    let sessions = load_sessions(force_dir)?;
+    Ok(sessions.into_values().collect())
+}
+
+/// Look up a single session by feature name or slug
+pub fn get_session(
+    force_dir: &Path,
+    name: &str,
+) -> Result<Option<Session>, Box<dyn std::error::Error>> {
+    let mut sessions = load_sessions(force_dir)?;
+    if let Some(session) = sessions.remove(name) {
+        return Ok(Some(session));
+    }
+    Ok(sessions.into_values().find(|s| s.slug == name))
+}
+
+/// Find the session using a slug, whatever its feature name
+pub fn slug_owner(
+    force_dir: &Path,
+    slug: &str,
+) -> Result<Option<Session>, Box<dyn std::error::Error>> {
     let sessions = load_sessions(force_dir)?;
-    let mut list: Vec<String> = sessions.into_iter().collect();
-    list.sort();
-    Ok(list)
+    Ok(sessions.into_values().find(|s| s.slug == slug))
 }
 
-/// Load sessions from file
-fn load_sessions(force_dir: &Path) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
-    let path = sessions_file(force_dir);
+/// Load sessions from the state file, migrating plain-text state if needed
+fn load_sessions(
+    force_dir: &Path,
+) -> Result<BTreeMap<String, Session>, Box<dyn std::error::Error>> {
+    let path = state_file(force_dir);
     if !path.exists() {
-        return Ok(HashSet::new());
+        return migrate_legacy_sessions(force_dir);
     }
 
     let content = fs::read_to_string(&path)?;
-    let sessions: HashSet<String> = content
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.trim().to_string())
-        .collect();
+    let state: StateFile = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    if state.version > STATE_VERSION {
+        return Err(format!(
+            "{} was written by a newer version of force (state version {})",
+            path.display(),
+            state.version
+        )
+        .into());
+    }
+
+    Ok(state
+        .sessions
+        .into_iter()
+        .map(|s| (s.feature.clone(), s))
+        .collect())
+}
+
+/// Convert the plain-text sessions file into structured state.
+///
+/// Each legacy line is `feature` or `feature<TAB>port_offset`. Fields the old
+/// format didn't record are filled in from the current config.
+fn migrate_legacy_sessions(
+    force_dir: &Path,
+) -> Result<BTreeMap<String, Session>, Box<dyn std::error::Error>> {
+    let legacy_path = legacy_sessions_file(force_dir);
+    if !legacy_path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let _lock = lock(force_dir)?;
+    // Another run may have migrated while this one waited for the lock
+    if !legacy_path.exists() {
+        return load_sessions(force_dir);
+    }
+
+    let force_config = config::load_config(force_dir)?;
+    let project_root = force_dir.parent().unwrap_or(force_dir);
+    let created_at = now();
+
+    let content = fs::read_to_string(&legacy_path)?;
+    let mut sessions = BTreeMap::new();
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let (feature, port_offset) = match line.rsplit_once('\t') {
+            Some((feature, offset)) => {
+                let offset = offset.trim().parse().map_err(|_| {
+                    format!("Invalid port offset in {}: {}", legacy_path.display(), line)
+                })?;
+                (feature.trim(), offset)
+            }
+            None => (line.trim(), hash_to_offset(line.trim(), DEFAULT_PORT_RANGE)),
+        };
+        // Worktrees and branches were named with the slug rule of the time
+        let slug = legacy_slugify(feature);
+        let session = Session {
+            feature: feature.to_string(),
+            port_offset,
+            port: force_config.ports.base + port_offset,
+            ports: force_config
+                .ports
+                .named
+                .iter()
+                .map(|(name, base)| (name.clone(), base + port_offset))
+                .collect(),
+            worktree: worktree::resolve_worktree_path(
+                project_root,
+                &slug,
+                &force_config.worktree.path,
+            ),
+            branch: slug.clone(),
+            slug,
+            db_name: None,
+            created_at,
+            last_up_at: None,
+            status: SessionStatus::Up,
+            services: Vec::new(),
+            files: Vec::new(),
+            seeded: Vec::new(),
+            adopted: false,
+            created_branch: false,
+            base: None,
+            owner: None,
+        };
+        sessions.insert(session.feature.clone(), session);
+    }
+
+    save_sessions(force_dir, &sessions)?;
+    fs::remove_file(&legacy_path)?;
 
     Ok(sessions)
 }
 
-/// Save sessions to file
+/// Save sessions to the state file
 fn save_sessions(
     force_dir: &Path,
-    sessions: &HashSet<String>,
+    sessions: &BTreeMap<String, Session>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let path = sessions_file(force_dir);
-    let content: String = sessions
-        .iter()
-        .map(|s| s.as_str())
-        .collect::<Vec<_>>()
-        .join("\n");
+    let path = state_file(force_dir);
 
     if sessions.is_empty() {
         // Remove file if no sessions
         let _ = fs::remove_file(&path);
-    } else {
-        fs::write(&path, content)?;
+        return Ok(());
     }
 
+    let state = StateFile {
+        version: STATE_VERSION,
+        sessions: sessions.values().cloned().collect(),
+    };
+    let content = serde_json::to_string_pretty(&state)?;
+
+    // Write to a temporary file first so a crash never leaves a torn state file
+    fs::create_dir_all(get_state_dir(force_dir))?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, &path)?;
+
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::env::slugify;
     use tempfile::TempDir;
 
+    fn test_session(feature: &str, port_offset: u16) -> Session {
+        Session {
+            feature: feature.to_string(),
+            slug: slugify(feature),
+            db_name: None,
+            port_offset,
+            port: 4000 + port_offset,
+            ports: BTreeMap::new(),
+            worktree: PathBuf::from("/worktrees").join(slugify(feature)),
+            branch: slugify(feature),
+            created_at: 1_700_000_000,
+            last_up_at: Some(1_700_000_100),
+            status: SessionStatus::Up,
+            services: Vec::new(),
+            files: Vec::new(),
+            seeded: Vec::new(),
+            adopted: false,
+            created_branch: false,
+            base: None,
+            owner: None,
+        }
+    }
+
     #[test]
     fn test_simple_hash_deterministic() {
         let hash1 = simple_hash("/path/to/project/.force");
@@ -123,13 +481,13 @@ mod tests {
         let force_dir = dir.path().join(".force");
         fs::create_dir(&force_dir).unwrap();
 
-        add_session(&force_dir, "feature-a").unwrap();
-        add_session(&force_dir, "feature-b").unwrap();
+        save_session(&force_dir, &test_session("feature-a", 1)).unwrap();
+        save_session(&force_dir, &test_session("feature-b", 2)).unwrap();
 
         let sessions = list_sessions(&force_dir).unwrap();
         assert_eq!(sessions.len(), 2);
-        assert!(sessions.contains(&"feature-a".to_string()));
-        assert!(sessions.contains(&"feature-b".to_string()));
+        assert_eq!(sessions[0], test_session("feature-a", 1));
+        assert_eq!(sessions[1], test_session("feature-b", 2));
     }
 
     #[test]
@@ -138,13 +496,49 @@ mod tests {
         let force_dir = dir.path().join(".force");
         fs::create_dir(&force_dir).unwrap();
 
-        add_session(&force_dir, "feature-a").unwrap();
-        add_session(&force_dir, "feature-b").unwrap();
+        save_session(&force_dir, &test_session("feature-a", 1)).unwrap();
+        save_session(&force_dir, &test_session("feature-b", 2)).unwrap();
         remove_session(&force_dir, "feature-a").unwrap();
 
         let sessions = list_sessions(&force_dir).unwrap();
         assert_eq!(sessions.len(), 1);
-        assert!(sessions.contains(&"feature-b".to_string()));
+        assert_eq!(sessions[0].feature, "feature-b");
+    }
+
+    #[test]
+    fn test_rename_session() {
+        let dir = TempDir::new().unwrap();
+        let force_dir = dir.path().join(".force");
+        fs::create_dir(&force_dir).unwrap();
+
+        save_session(&force_dir, &test_session("feature-a", 1)).unwrap();
+        rename_session(&force_dir, "feature-a", &test_session("feature-c", 1)).unwrap();
+
+        let sessions = list_sessions(&force_dir).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].feature, "feature-c");
+        assert_eq!(sessions[0].port_offset, 1);
+    }
+
+    #[test]
+    fn test_get_session_by_feature_or_slug() {
+        let dir = TempDir::new().unwrap();
+        let force_dir = dir.path().join(".force");
+        fs::create_dir(&force_dir).unwrap();
+
+        save_session(&force_dir, &test_session("Add Login", 1)).unwrap();
+
+        let by_feature = get_session(&force_dir, "Add Login").unwrap().unwrap();
+        let by_slug = get_session(&force_dir, "add_login").unwrap().unwrap();
+        assert_eq!(by_feature, by_slug);
+        assert!(get_session(&force_dir, "add-login").unwrap().is_none());
+        assert_eq!(
+            slug_owner(&force_dir, "add_login")
+                .unwrap()
+                .unwrap()
+                .feature,
+            "Add Login"
+        );
     }
 
     #[test]
@@ -163,13 +557,113 @@ mod tests {
         let force_dir = dir.path().join(".force");
         fs::create_dir(&force_dir).unwrap();
 
-        add_session(&force_dir, "feature-a").unwrap();
-        add_session(&force_dir, "feature-a").unwrap();
+        save_session(&force_dir, &test_session("feature-a", 1)).unwrap();
+        save_session(&force_dir, &test_session("feature-a", 1)).unwrap();
 
         let sessions = list_sessions(&force_dir).unwrap();
         assert_eq!(sessions.len(), 1);
     }
 
+    #[test]
+    fn test_save_session_replaces_record() {
+        let dir = TempDir::new().unwrap();
+        let force_dir = dir.path().join(".force");
+        fs::create_dir(&force_dir).unwrap();
+
+        save_session(&force_dir, &test_session("feature-a", 1)).unwrap();
+        let mut updated = test_session("feature-a", 7);
+        updated.status = SessionStatus::Failed;
+        save_session(&force_dir, &updated).unwrap();
+
+        let session = get_session(&force_dir, "feature-a").unwrap().unwrap();
+        assert_eq!(session.port_offset, 7);
+        assert_eq!(session.status, SessionStatus::Failed);
+        assert!(get_session(&force_dir, "missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_state_file_is_versioned_json() {
+        let dir = TempDir::new().unwrap();
+        let force_dir = dir.path().join(".force");
+        fs::create_dir(&force_dir).unwrap();
+
+        save_session(&force_dir, &test_session("feature-a", 1)).unwrap();
+
+        let content = fs::read_to_string(state_file(&force_dir)).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(value["version"], STATE_VERSION);
+        assert_eq!(value["sessions"][0]["feature"], "feature-a");
+        assert_eq!(value["sessions"][0]["status"], "up");
+    }
+
+    #[test]
+    fn test_concurrent_saves_keep_every_session() {
+        let dir = TempDir::new().unwrap();
+        let force_dir = dir.path().join(".force");
+        fs::create_dir(&force_dir).unwrap();
+
+        std::thread::scope(|scope| {
+            for writer in 0..8u16 {
+                let force_dir = &force_dir;
+                scope.spawn(move || {
+                    for i in 0..10 {
+                        let feature = format!("feature-{}-{}", writer, i);
+                        save_session(force_dir, &test_session(&feature, writer * 10 + i)).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(list_sessions(&force_dir).unwrap().len(), 80);
+    }
+
+    #[test]
+    fn test_rejects_newer_state_version() {
+        let dir = TempDir::new().unwrap();
+        let force_dir = dir.path().join(".force");
+        fs::create_dir(&force_dir).unwrap();
+        fs::create_dir_all(get_state_dir(&force_dir)).unwrap();
+        fs::write(
+            state_file(&force_dir),
+            r#"{"version": 999, "sessions": []}"#,
+        )
+        .unwrap();
+
+        assert!(list_sessions(&force_dir).is_err());
+    }
+
+    #[test]
+    fn test_migrates_legacy_sessions() {
+        let dir = TempDir::new().unwrap();
+        let force_dir = dir.path().join(".force");
+        fs::create_dir(&force_dir).unwrap();
+        fs::create_dir_all(get_state_dir(&force_dir)).unwrap();
+        fs::write(
+            legacy_sessions_file(&force_dir),
+            "legacy-feature\nwith-offset\t12\n",
+        )
+        .unwrap();
+
+        let sessions = list_sessions(&force_dir).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].feature, "legacy-feature");
+        assert_eq!(
+            sessions[0].port_offset,
+            hash_to_offset("legacy-feature", DEFAULT_PORT_RANGE)
+        );
+        assert_eq!(sessions[0].slug, "legacy_feature");
+        assert_eq!(sessions[0].branch, "legacy_feature");
+        assert_eq!(sessions[0].status, SessionStatus::Up);
+        assert!(sessions[0].worktree.ends_with("worktrees/legacy_feature"));
+        assert_eq!(sessions[1].feature, "with-offset");
+        assert_eq!(sessions[1].port_offset, 12);
+        assert_eq!(sessions[1].port, 4012);
+
+        // The legacy file is replaced by the structured state file
+        assert!(!legacy_sessions_file(&force_dir).exists());
+        assert!(state_file(&force_dir).exists());
+    }
+
     #[test]
     fn test_state_dir_is_absolute_path() {
         let dir = TempDir::new().unwrap();
@@ -1,26 +1,121 @@
+use crate::config::WorktreeConfig;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// A linked worktree reported by `git worktree list`
+#[derive(Debug, PartialEq)]
+pub struct WorktreeEntry {
+    pub path: PathBuf,
+    /// Checked-out branch, without `refs/heads/`
+    pub branch: Option<String>,
+    /// Git considers the worktree prunable (its directory is gone)
+    pub prunable: bool,
+}
+
 pub struct WorktreeResult {
     pub path: PathBuf,
+    pub branch: String,
     pub created: bool, // true if newly created, false if reused
+    /// The worktree wasn't made by Force, so Force must not remove it
+    pub adopted: bool,
+    /// The branch was created for this worktree with `git worktree add -b`
+    pub created_branch: bool,
+    /// What a created branch started from, for `delete_branch = "merged"`
+    pub base: Option<String>,
 }
 
-/// Create a git worktree for the feature
+/// Where a new worktree branch starts
+#[derive(Clone, Copy)]
+pub enum StartPoint<'a> {
+    /// A base ref, or the main checkout's HEAD if none
+    Base(Option<&'a str>),
+    /// A remote-tracking branch such as `origin/feature`, which the new
+    /// branch tracks
+    Remote(&'a str),
+    /// Any ref already fetched, such as `refs/pull/12/head`
+    Ref(&'a str),
+}
+
+impl StartPoint<'_> {
+    /// The commit-ish to hand to git, if any
+    fn rev(&self) -> Option<String> {
+        match self {
+            StartPoint::Base(base) => base.map(String::from),
+            StartPoint::Remote(remote) => Some(format!(
+                "refs/remotes/{}",
+                remote.strip_prefix("refs/remotes/").unwrap_or(remote)
+            )),
+            StartPoint::Ref(rev) => Some(rev.to_string()),
+        }
+    }
+
+    /// The flag that asked for this start point and its value, for messages
+    fn flag(&self) -> (&'static str, &str) {
+        match self {
+            StartPoint::Base(base) => ("--base", base.unwrap_or("HEAD")),
+            StartPoint::Remote(remote) => ("--from", remote),
+            StartPoint::Ref(rev) => ("--from-ref", rev),
+        }
+    }
+
+    fn missing_error(&self) -> String {
+        match self {
+            StartPoint::Base(base) => format!(
+                "Base '{}' is not a commit in this repository (try `git fetch`)",
+                base.unwrap_or("HEAD")
+            ),
+            StartPoint::Remote(remote) => {
+                let remote_name = remote.split('/').next().unwrap_or(remote);
+                format!(
+                    "Remote branch '{}' not found locally. Fetch it first: git fetch {}",
+                    remote, remote_name
+                )
+            }
+            StartPoint::Ref(rev) => format!(
+                "Ref '{}' not found locally. Fetch it first, e.g.: git fetch origin {}:{}",
+                rev, rev, rev
+            ),
+        }
+    }
+}
+
+/// Create a git worktree for the feature.
+///
+/// A new branch named from the `branch` template is started at `start`.
+/// An existing branch with that name is checked out as-is.
+/// `existing_branch` checks out that branch instead, which must exist.
 pub fn create_worktree(
     project_root: &Path,
     feature_slug: &str,
-    path_template: &str,
+    config: &WorktreeConfig,
+    start: StartPoint,
+    existing_branch: Option<&str>,
 ) -> Result<WorktreeResult, Box<dyn std::error::Error>> {
-    let worktree_path = expand_path_template(path_template, feature_slug);
+    let worktree_path = expand_template(&config.path, feature_slug);
     let absolute_path = resolve_path(project_root, &worktree_path);
+    let branch = match existing_branch {
+        Some(branch) => {
+            if !branch_exists(project_root, branch)? {
+                return Err(format!("Branch '{}' does not exist", branch).into());
+            }
+            branch.to_string()
+        }
+        None => expand_template(&config.branch, feature_slug),
+    };
 
     // Check if worktree already exists
     if absolute_path.exists() {
         if is_valid_worktree(&absolute_path) {
+            if branch_exists(project_root, &branch)? {
+                require_start_point(project_root, &branch, start)?;
+            }
             return Ok(WorktreeResult {
                 path: absolute_path,
+                branch,
                 created: false,
+                adopted: false,
+                created_branch: false,
+                base: None,
             });
         } else {
             return Err(format!(
@@ -31,49 +126,315 @@ pub fn create_worktree(
         }
     }
 
+    if !git_succeeds(project_root, &["check-ref-format", "--branch", &branch])? {
+        return Err(format!("'{}' is not a valid branch name", branch).into());
+    }
+    let start_rev = start.rev();
+    if let Some(rev) = &start_rev
+        && !git_succeeds(
+            project_root,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{}^{{commit}}", rev),
+            ],
+        )?
+    {
+        return Err(start.missing_error().into());
+    }
+
     // Create parent directories if needed
     if let Some(parent) = absolute_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
-    // Create the worktree with a new branch
+    let path_arg = absolute_path.to_string_lossy();
+    let new_branch = !branch_exists(project_root, &branch)?;
+    let args: Vec<&str> = if !new_branch {
+        require_start_point(project_root, &branch, start)?;
+        println!("Using existing branch '{}'", branch);
+        if let StartPoint::Base(Some(_)) = start
+            && let Some(rev) = &start_rev
+        {
+            println!(
+                "Branch '{}' already exists, ignoring start point '{}'",
+                branch, rev
+            );
+        }
+        vec!["worktree", "add", &path_arg, &branch]
+    } else {
+        // Only a remote branch picked with --from becomes the upstream;
+        // --no-track keeps a base like origin/main from becoming it
+        let track = match start {
+            StartPoint::Remote(_) => "--track",
+            _ => "--no-track",
+        };
+        let mut args = vec!["worktree", "add", track, "-b", &branch, &path_arg];
+        args.extend(start_rev.as_deref());
+        args
+    };
+
     let output = Command::new("git")
-        .args([
-            "worktree",
-            "add",
-            &absolute_path.to_string_lossy(),
-            "-b",
-            feature_slug,
-        ])
+        .args(&args)
         .current_dir(project_root)
         .output()?;
 
     if !output.status.success() {
-        // Try without -b in case branch already exists
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Failed to create worktree. Branch '{}' may exist in another worktree.\n{}",
+            branch, stderr
+        )
+        .into());
+    }
+
+    if let Err(e) = init_checkout(&absolute_path, config) {
+        // Start from scratch on the next `force up` rather than reuse a
+        // worktree that was only half set up
+        let _ = remove_worktree(project_root, &absolute_path);
+        if new_branch {
+            let _ = delete_branch(project_root, &branch);
+        }
+        return Err(e);
+    }
+
+    let base = match (new_branch, start_rev) {
+        (false, _) => None,
+        (true, Some(rev)) => Some(rev),
+        (true, None) => Some(head_ref(project_root)?),
+    };
+    Ok(WorktreeResult {
+        path: absolute_path,
+        branch,
+        created: true,
+        adopted: false,
+        created_branch: new_branch,
+        base,
+    })
+}
+
+/// The main checkout's branch, or its commit if HEAD is detached, so a later
+/// checkout of another branch doesn't change what HEAD meant
+fn head_ref(project_root: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let branch = git_output(
+        project_root,
+        &["symbolic-ref", "--quiet", "--short", "HEAD"],
+    )
+    .unwrap_or_default();
+    if !branch.trim().is_empty() {
+        return Ok(branch.trim().to_string());
+    }
+    Ok(git_output(project_root, &["rev-parse", "HEAD"])?
+        .trim()
+        .to_string())
+}
+
+/// Attach to a worktree of this repository that Force didn't create
+pub fn adopt_worktree(
+    project_root: &Path,
+    path: &Path,
+) -> Result<WorktreeResult, Box<dyn std::error::Error>> {
+    let entry = list_worktrees(project_root)?
+        .into_iter()
+        .find(|entry| same_path(&entry.path, path))
+        .ok_or_else(|| {
+            format!(
+                "{} is not a linked worktree of this repository (see `git worktree list`)",
+                path.display()
+            )
+        })?;
+
+    Ok(WorktreeResult {
+        path: entry.path,
+        branch: entry.branch.unwrap_or_else(|| "HEAD".to_string()),
+        created: false,
+        adopted: true,
+        created_branch: false,
+        base: None,
+    })
+}
+
+/// The linked worktree that has `branch` checked out, if any
+pub fn find_by_branch(
+    project_root: &Path,
+    branch: &str,
+) -> Result<Option<WorktreeEntry>, Box<dyn std::error::Error>> {
+    Ok(list_worktrees(project_root)?
+        .into_iter()
+        .find(|entry| entry.branch.as_deref() == Some(branch)))
+}
+
+/// Check out submodules and LFS objects in a new worktree, as configured
+fn init_checkout(
+    worktree_path: &Path,
+    config: &WorktreeConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut steps: Vec<(&[&str], &str)> = Vec::new();
+    if config.submodules {
+        steps.push((
+            &["submodule", "update", "--init", "--recursive"],
+            "initialize submodules",
+        ));
+    }
+    if config.lfs {
+        steps.push((&["lfs", "pull"], "fetch LFS objects"));
+    }
+
+    for (args, what) in steps {
+        println!("Running git {}", args.join(" "));
         let output = Command::new("git")
-            .args([
-                "worktree",
-                "add",
-                &absolute_path.to_string_lossy(),
-                feature_slug,
-            ])
-            .current_dir(project_root)
+            .args(args)
+            .current_dir(worktree_path)
             .output()?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             return Err(format!(
-                "Failed to create worktree. Branch '{}' may exist in another worktree.\n{}",
-                feature_slug, stderr
+                "Failed to {} in worktree {}.\n{}",
+                what,
+                worktree_path.display(),
+                stderr
             )
             .into());
         }
     }
 
-    Ok(WorktreeResult {
-        path: absolute_path,
-        created: true,
-    })
+    Ok(())
+}
+
+/// Move a worktree with `git worktree move`
+pub fn move_worktree(
+    project_root: &Path,
+    from: &Path,
+    to: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    git_output(
+        project_root,
+        &[
+            "worktree",
+            "move",
+            &from.to_string_lossy(),
+            &to.to_string_lossy(),
+        ],
+    )
+    .map_err(|e| format!("Failed to move worktree to {}: {}", to.display(), e))?;
+    Ok(())
+}
+
+/// Rename a local branch, even one checked out in a worktree
+pub fn rename_branch(
+    project_root: &Path,
+    from: &str,
+    to: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    git_output(project_root, &["branch", "-m", from, to])
+        .map_err(|e| format!("Failed to rename branch '{}': {}", from, e))?;
+    Ok(())
+}
+
+/// Whether a local branch exists
+pub fn branch_exists(
+    project_root: &Path,
+    branch: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    git_succeeds(
+        project_root,
+        &[
+            "show-ref",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{}", branch),
+        ],
+    )
+}
+
+/// Whether every commit on `branch` is also on `base`
+pub fn is_merged(
+    project_root: &Path,
+    branch: &str,
+    base: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    git_output(
+        project_root,
+        &["rev-parse", "--verify", &format!("{}^{{commit}}", base)],
+    )
+    .map_err(|_| format!("Base '{}' is not a commit in this repository", base))?;
+    git_succeeds(project_root, &["merge-base", "--is-ancestor", branch, base])
+}
+
+/// Delete a local branch, merged or not
+pub fn delete_branch(project_root: &Path, branch: &str) -> Result<(), Box<dyn std::error::Error>> {
+    git_output(project_root, &["branch", "-D", branch])
+        .map_err(|e| format!("Failed to delete branch '{}': {}", branch, e))?;
+    Ok(())
+}
+
+/// Recover the feature slug from a branch name made with `branch_template`
+pub fn slug_from_branch(branch: &str, branch_template: &str) -> Option<String> {
+    let (prefix, suffix) = branch_template.split_once("$FORCE_FEATURE_SLUG")?;
+    branch
+        .strip_prefix(prefix)?
+        .strip_suffix(suffix)
+        .filter(|slug| !slug.is_empty())
+        .map(String::from)
+}
+
+/// `--from` and `--from-ref` name exactly what to check out, so an existing
+/// branch is only used if it already points there. A branch matching `--from`
+/// is set to track the remote branch. A base is only a default and is never
+/// enforced.
+pub fn require_start_point(
+    project_root: &Path,
+    branch: &str,
+    start: StartPoint,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(rev) = start
+        .rev()
+        .filter(|_| !matches!(start, StartPoint::Base(_)))
+    else {
+        return Ok(());
+    };
+    let commit = |rev: &str| {
+        git_output(
+            project_root,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{}^{{commit}}", rev),
+            ],
+        )
+        .ok()
+        .map(|out| out.trim().to_string())
+    };
+    let wanted = commit(&rev).ok_or_else(|| start.missing_error())?;
+    if commit(&format!("refs/heads/{}", branch)) != Some(wanted) {
+        let (flag, value) = start.flag();
+        return Err(format!(
+            "Branch '{}' already exists and isn't at {} {}. \
+             Delete or rename the branch first, or drop {} to use it as it is.",
+            branch, flag, value, flag
+        )
+        .into());
+    }
+
+    if let StartPoint::Remote(_) = start {
+        git_output(project_root, &["branch", "--set-upstream-to", &rev, branch])?;
+    }
+    Ok(())
+}
+
+/// Run a git command, reporting only whether it succeeded
+fn git_succeeds(project_root: &Path, args: &[&str]) -> Result<bool, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(project_root)
+        .output()?;
+    Ok(output.status.success())
 }
 
 /// Resolve worktree path without creating it
@@ -82,10 +443,176 @@ pub fn resolve_worktree_path(
     feature_slug: &str,
     path_template: &str,
 ) -> PathBuf {
-    let worktree_path = expand_path_template(path_template, feature_slug);
+    let worktree_path = expand_template(path_template, feature_slug);
     resolve_path(project_root, &worktree_path)
 }
 
+/// Work in a worktree that removing it would throw away
+pub struct UnsavedWork {
+    /// Modified, deleted, and untracked paths relative to the worktree
+    pub changes: Vec<String>,
+    /// Commits on the worktree's HEAD that aren't on any other branch
+    pub commits: usize,
+}
+
+impl UnsavedWork {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.commits == 0
+    }
+}
+
+impl std::fmt::Display for UnsavedWork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if !self.changes.is_empty() {
+            parts.push(format!("{} uncommitted change(s)", self.changes.len()));
+        }
+        if self.commits > 0 {
+            parts.push(format!(
+                "{} commit(s) not on any other branch",
+                self.commits
+            ));
+        }
+        write!(f, "{}", parts.join(" and "))
+    }
+}
+
+/// Find uncommitted changes and unpushed commits in a worktree.
+///
+/// Paths in or under `ignore` (absolute, e.g. files Force generated) don't
+/// count as changes. Commits count as unpushed unless another local or
+/// remote-tracking branch contains them.
+pub fn unsaved_work(
+    worktree_path: &Path,
+    ignore: &[&Path],
+) -> Result<UnsavedWork, Box<dyn std::error::Error>> {
+    let status = git_output(
+        worktree_path,
+        &["status", "--porcelain", "-z", "--untracked-files=all"],
+    )?;
+    let changes = parse_status(&status)
+        .into_iter()
+        .filter(|path| {
+            let path = worktree_path.join(path);
+            !ignore.iter().any(|ignored| path.starts_with(ignored))
+        })
+        .collect();
+
+    let branch = git_output(
+        worktree_path,
+        &["symbolic-ref", "--quiet", "--short", "HEAD"],
+    )
+    .unwrap_or_default();
+    let exclude = format!("--exclude={}", branch.trim());
+    let mut args = vec!["rev-list", "--count", "HEAD", "--not"];
+    if !branch.trim().is_empty() {
+        args.push(&exclude);
+    }
+    args.extend(["--branches", "--remotes"]);
+    let commits = git_output(worktree_path, &args)?.trim().parse()?;
+
+    Ok(UnsavedWork { changes, commits })
+}
+
+/// Paths from `git status --porcelain -z` output
+fn parse_status(output: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        let (status, path) = entry.split_at(entry.len().min(3));
+        paths.push(path.to_string());
+        // Renames and copies are followed by their source path
+        if status.starts_with(['R', 'C']) {
+            paths.extend(entries.next().map(String::from));
+        }
+    }
+    paths
+}
+
+/// Stash the given paths, untracked files included. Stashes are shared by all
+/// worktrees, so they outlive the worktree.
+pub fn stash_changes(
+    worktree_path: &Path,
+    paths: &[String],
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = vec!["stash", "push", "--include-untracked", "-m", message, "--"];
+    args.extend(paths.iter().map(String::as_str));
+    git_output(worktree_path, &args)?;
+    Ok(())
+}
+
+/// Save the worktree's HEAD plus any uncommitted changes to
+/// `refs/force/backup/<slug>`, without touching its branch or index.
+/// Returns the ref name.
+pub fn backup_worktree(
+    worktree_path: &Path,
+    feature_slug: &str,
+    ignore: &[&Path],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let refname = format!("refs/force/backup/{}", feature_slug);
+    let head = git_output(worktree_path, &["rev-parse", "HEAD"])?;
+    let head = head.trim();
+
+    // Build the tree in a copy of the index so the real one is left alone
+    let index = std::env::temp_dir().join(format!("force-backup-{}.index", std::process::id()));
+    let real_index = git_output(
+        worktree_path,
+        &["rev-parse", "--path-format=absolute", "--git-path", "index"],
+    )?;
+    if std::fs::copy(real_index.trim(), &index).is_err() {
+        let _ = std::fs::remove_file(&index);
+    }
+    let excludes: Vec<String> = ignore
+        .iter()
+        .filter_map(|path| path.strip_prefix(worktree_path).ok())
+        .map(|path| format!(":(exclude,literal){}", path.display()))
+        .collect();
+    let mut add_args = vec!["add", "--all", "--", "."];
+    add_args.extend(excludes.iter().map(String::as_str));
+    let tree = git_with_index(worktree_path, &index, &add_args)
+        .and_then(|_| git_with_index(worktree_path, &index, &["write-tree"]));
+    let _ = std::fs::remove_file(&index);
+    let tree = tree?;
+
+    let message = format!("force backup of {}", feature_slug);
+    let commit = git_output(
+        worktree_path,
+        &["commit-tree", tree.trim(), "-p", head, "-m", &message],
+    )?;
+    git_output(worktree_path, &["update-ref", &refname, commit.trim()])?;
+    Ok(refname)
+}
+
+/// Run a git command and return its stdout, failing with its stderr
+fn git_output(dir: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    stdout_of(Command::new("git").args(args).current_dir(dir), args)
+}
+
+/// Like `git_output`, but with `index` in place of the worktree's index
+fn git_with_index(
+    dir: &Path,
+    index: &Path,
+    args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    stdout_of(
+        Command::new("git")
+            .args(args)
+            .env("GIT_INDEX_FILE", index)
+            .current_dir(dir),
+        args,
+    )
+}
+
+fn stdout_of(command: &mut Command, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args[0], stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
 /// Remove a git worktree
 pub fn remove_worktree(
     project_root: &Path,
@@ -118,7 +645,97 @@ pub fn remove_worktree(
     Ok(())
 }
 
-fn expand_path_template(template: &str, feature_slug: &str) -> String {
+/// List the repository's linked worktrees, excluding the main checkout
+pub fn list_worktrees(
+    project_root: &Path,
+) -> Result<Vec<WorktreeEntry>, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .args(["worktree", "list", "--porcelain"])
+        .current_dir(project_root)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list worktrees: {}", stderr).into());
+    }
+
+    let mut entries = parse_worktree_list(&String::from_utf8_lossy(&output.stdout));
+    // The first entry is always the main worktree
+    if !entries.is_empty() {
+        entries.remove(0);
+    }
+    Ok(entries)
+}
+
+fn parse_worktree_list(output: &str) -> Vec<WorktreeEntry> {
+    let mut entries = Vec::new();
+    let mut current: Option<WorktreeEntry> = None;
+
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            entries.extend(current.take());
+            current = Some(WorktreeEntry {
+                path: PathBuf::from(path),
+                branch: None,
+                prunable: false,
+            });
+        } else if let Some(entry) = current.as_mut() {
+            if let Some(branch) = line.strip_prefix("branch ") {
+                let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+                entry.branch = Some(branch.to_string());
+            } else if line == "prunable" || line.starts_with("prunable ") {
+                entry.prunable = true;
+            }
+        }
+    }
+    entries.extend(current);
+    entries
+}
+
+/// Remove git's records of worktrees whose directories are gone
+pub fn prune_worktrees(project_root: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .args(["worktree", "prune"])
+        .current_dir(project_root)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to prune worktrees: {}", stderr).into());
+    }
+
+    Ok(())
+}
+
+/// Whether two paths point at the same location, resolving symlinks and
+/// `..` where the paths exist
+pub fn same_path(a: &Path, b: &Path) -> bool {
+    normalize(a) == normalize(b)
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            std::path::Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    // Resolve symlinks in the part of the path that does exist
+    match (normalized.parent(), normalized.file_name()) {
+        (Some(parent), Some(name)) if parent.exists() => normalize(parent).join(name),
+        _ => normalized,
+    }
+}
+
+/// Substitute the feature slug into a path or branch template
+pub fn expand_template(template: &str, feature_slug: &str) -> String {
     template.replace("$FORCE_FEATURE_SLUG", feature_slug)
 }
 
@@ -131,7 +748,7 @@ fn resolve_path(project_root: &Path, relative_path: &str) -> PathBuf {
     }
 }
 
-fn is_valid_worktree(path: &Path) -> bool {
+pub fn is_valid_worktree(path: &Path) -> bool {
     // Worktrees have a .git file (not directory) that points to the main repo
     let git_path = path.join(".git");
     git_path.exists()
@@ -141,14 +758,89 @@ fn is_valid_worktree(path: &Path) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_status() {
+        let output = " M src/lib.rs\0?? notes.txt\0R  new.rs\0old.rs\0 D gone.rs\0";
+        assert_eq!(
+            parse_status(output),
+            vec!["src/lib.rs", "notes.txt", "new.rs", "old.rs", "gone.rs"]
+        );
+        assert!(parse_status("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_worktree_list() {
+        let output = "worktree /repo\nHEAD abc\nbranch refs/heads/main\n\n\
+                      worktree /worktrees/login\nHEAD def\nbranch refs/heads/login\n\n\
+                      worktree /worktrees/gone\nHEAD 123\ndetached\nprunable gitdir file points to non-existent location\n";
+        let entries = parse_worktree_list(output);
+        assert_eq!(
+            entries,
+            vec![
+                WorktreeEntry {
+                    path: PathBuf::from("/repo"),
+                    branch: Some("main".to_string()),
+                    prunable: false,
+                },
+                WorktreeEntry {
+                    path: PathBuf::from("/worktrees/login"),
+                    branch: Some("login".to_string()),
+                    prunable: false,
+                },
+                WorktreeEntry {
+                    path: PathBuf::from("/worktrees/gone"),
+                    branch: None,
+                    prunable: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_path_handles_parent_components() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+
+        assert!(same_path(
+            &project.join("../worktrees/login"),
+            &dir.path().join("worktrees/login")
+        ));
+        assert!(!same_path(
+            &project.join("../worktrees/login"),
+            &dir.path().join("worktrees/logout")
+        ));
+    }
+
+    #[test]
+    fn test_slug_from_branch() {
+        assert_eq!(
+            slug_from_branch("agent/login", "agent/$FORCE_FEATURE_SLUG"),
+            Some("login".to_string())
+        );
+        assert_eq!(
+            slug_from_branch("login", "$FORCE_FEATURE_SLUG"),
+            Some("login".to_string())
+        );
+        assert_eq!(
+            slug_from_branch("feature/login-wip", "feature/$FORCE_FEATURE_SLUG-wip"),
+            Some("login".to_string())
+        );
+        assert_eq!(slug_from_branch("main", "agent/$FORCE_FEATURE_SLUG"), None);
+        assert_eq!(
+            slug_from_branch("agent/", "agent/$FORCE_FEATURE_SLUG"),
+            None
+        );
+    }
+
     #[test]
     fn test_expand_path_template() {
         assert_eq!(
-            expand_path_template("../worktrees/$FORCE_FEATURE_SLUG", "my_feature"),
+            expand_template("../worktrees/$FORCE_FEATURE_SLUG", "my_feature"),
             "../worktrees/my_feature"
         );
         assert_eq!(
-            expand_path_template(".worktrees/$FORCE_FEATURE_SLUG", "test"),
+            expand_template(".worktrees/$FORCE_FEATURE_SLUG", "test"),
             ".worktrees/test"
         );
     }
@@ -0,0 +1,290 @@
+use assert_cmd::assert::Assert;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper to run force command
+fn force_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("force"))
+}
+
+// Helper functions - creates a git repo with initial commit for worktree support
+fn create_temp_project() -> TempDir {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+
+    // Initialize git repo
+    Command::new("git")
+        .args(["init"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to init git");
+
+    // Configure git user for commits
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git email");
+
+    Command::new("git")
+        .args(["config", "user.name", "Test User"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git name");
+
+    // Create initial commit (required for worktrees)
+    Command::new("git")
+        .args(["commit", "--allow-empty", "-m", "Initial commit"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to create initial commit");
+
+    fs::create_dir(dir.path().join(".force")).expect("Failed to create .force dir");
+    dir
+}
+
+fn create_script(project_dir: &Path, name: &str, content: &str) {
+    let script_path = project_dir.join(".force").join(format!("{}.toml", name));
+    fs::write(&script_path, content).expect("Failed to write script file");
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+// Records the directory each up script ran in
+fn pwd_script(output_file: &Path) -> String {
+    format!(
+        r#"[meta]
+category = "setup"
+
+[up]
+run = "pwd -P > {}"
+"#,
+        output_file.display()
+    )
+}
+
+fn local_worktree_config(project: &Path) {
+    fs::write(
+        project.join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_up_with_existing_branch() {
+    let project = create_temp_project();
+    local_worktree_config(project.path());
+    create_script(
+        project.path(),
+        "pwd",
+        &pwd_script(&project.path().join("pwd.txt")),
+    );
+    git(project.path(), &["branch", "existing-work"]);
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "login", "--branch", "existing-work"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Using existing branch 'existing-work'",
+    ));
+
+    let worktree = project.path().join(".worktrees/login");
+    assert_eq!(
+        git(&worktree, &["rev-parse", "--abbrev-ref", "HEAD"]),
+        "existing-work"
+    );
+    git(&worktree, &["commit", "--allow-empty", "-m", "Only here"]);
+
+    // The worktree was Force's, but the branch wasn't
+    Assert::new(
+        force_cmd()
+            .args(["down", "login", "--force", "--delete-branch", "always"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Kept branch 'existing-work' (Force didn't create it)",
+    ));
+    assert!(!worktree.exists());
+    assert!(
+        !git(
+            project.path(),
+            &["rev-parse", "--verify", "--quiet", "existing-work"]
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+fn test_up_with_missing_branch_fails() {
+    let project = create_temp_project();
+    local_worktree_config(project.path());
+    create_script(
+        project.path(),
+        "pwd",
+        &pwd_script(&project.path().join("pwd.txt")),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "login", "--branch", "no-such-branch"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "Branch 'no-such-branch' does not exist",
+    ));
+}
+
+#[test]
+fn test_up_with_branch_adopts_its_worktree() {
+    let project = create_temp_project();
+    local_worktree_config(project.path());
+    let output = project.path().join("pwd.txt");
+    create_script(project.path(), "pwd", &pwd_script(&output));
+
+    let handmade = project.path().join("handmade");
+    git(
+        project.path(),
+        &["worktree", "add", "-b", "hand-branch", "handmade"],
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "hand", "--branch", "hand-branch"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Adopting worktree at"));
+    assert_eq!(
+        fs::read_to_string(&output).unwrap().trim(),
+        handmade.canonicalize().unwrap().to_string_lossy()
+    );
+
+    // Down leaves a worktree Force didn't create
+    Assert::new(
+        force_cmd()
+            .args(["down", "hand"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Keeping adopted worktree"));
+    assert!(handmade.exists());
+}
+
+#[test]
+fn test_adopt_worktree() {
+    let project = create_temp_project();
+    local_worktree_config(project.path());
+    let output = project.path().join("pwd.txt");
+    create_script(project.path(), "pwd", &pwd_script(&output));
+
+    let handmade = project.path().join("elsewhere/checkout");
+    git(
+        project.path(),
+        &["worktree", "add", "-b", "payments", "elsewhere/checkout"],
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["adopt", "elsewhere/checkout"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Session 'payments' is ready!"));
+    assert_eq!(
+        fs::read_to_string(&output).unwrap().trim(),
+        handmade.canonicalize().unwrap().to_string_lossy()
+    );
+
+    // Later commands use the recorded path, not the path template
+    Assert::new(
+        force_cmd()
+            .args(["ls"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("adopted"))
+    .stdout(predicate::str::contains("elsewhere/checkout"));
+
+    Assert::new(
+        force_cmd()
+            .args(["exec", "payments", "--", "pwd", "-P"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("elsewhere/checkout"));
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "payments"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Adopting worktree at"));
+    assert!(!project.path().join(".worktrees/payments").exists());
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "payments"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+    assert!(handmade.exists());
+    assert!(
+        !git(
+            project.path(),
+            &["rev-parse", "--verify", "--quiet", "payments"]
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+fn test_adopt_rejects_non_worktree() {
+    let project = create_temp_project();
+    fs::create_dir(project.path().join("plain")).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["adopt", "plain", "plain-dir"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("is not a linked worktree"));
+}
@@ -438,3 +438,604 @@ remove_on_down = false
         "Worktree should still exist when remove_on_down = false"
     );
 }
+
+#[test]
+fn test_down_runs_in_reverse_dependency_order() {
+    let project = create_temp_project();
+    let output_file = project.path().join("order.txt");
+    fs::write(&output_file, "").unwrap();
+    let abs_path = output_file.canonicalize().unwrap();
+
+    // "db" sorts before "setup", but migrate depends on database
+    let migrate = format!(
+        r#"[meta]
+category = "db"
+depends_on = ["database"]
+
+[up]
+run = "echo 'up'"
+
+[down]
+run = "echo 'migrate' >> {}"
+"#,
+        abs_path.display()
+    );
+    create_script(project.path(), "migrate", &migrate);
+    create_script(
+        project.path(),
+        "database",
+        &order_tracking_down_script("setup", None, "database", &output_file),
+    );
+
+    force_cmd()
+        .args(["up", "down-deps-test"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "down-deps-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let output = fs::read_to_string(&output_file).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines, vec!["migrate", "database"]);
+}
+
+fn is_alive(pid: &str) -> bool {
+    let output = Command::new("ps")
+        .args(["-o", "stat=", "-p", pid])
+        .output()
+        .unwrap();
+    let stat = String::from_utf8_lossy(&output.stdout);
+    let stat = stat.trim();
+    !stat.is_empty() && !stat.starts_with('Z')
+}
+
+#[test]
+fn test_down_stops_service_before_down_scripts() {
+    let project = create_temp_project();
+    let pid_file = project.path().join("service.pid");
+    let order_file = project.path().join("order.txt");
+    fs::write(&pid_file, "").unwrap();
+    fs::write(&order_file, "").unwrap();
+    let pid_file = pid_file.canonicalize().unwrap();
+    let order_file = order_file.canonicalize().unwrap();
+
+    let script = format!(
+        r#"[meta]
+category = "services"
+
+[up]
+run = "echo $$ > {pid}; exec sleep 1000"
+service = true
+
+[down]
+run = "if ps -o stat= -p $(cat {pid}) | grep -qv Z; then echo running >> {order}; else echo stopped >> {order}; fi"
+"#,
+        pid = pid_file.display(),
+        order = order_file.display()
+    );
+    create_script(project.path(), "server", &script);
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "down-service-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("started in background"));
+
+    // Wait for the service to write its pid
+    let mut pid = String::new();
+    for _ in 0..50 {
+        pid = fs::read_to_string(&pid_file).unwrap().trim().to_string();
+        if !pid.is_empty() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    assert!(is_alive(&pid), "Service should keep running after up");
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "down-service-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Stopping service 'server'"));
+
+    assert!(!is_alive(&pid), "Service should be stopped by down");
+    assert_eq!(fs::read_to_string(&order_file).unwrap().trim(), "stopped");
+}
+
+fn files_config() -> &'static str {
+    r#"[worktree]
+remove_on_down = false
+
+[[files]]
+path = ".dev.local.env"
+mode = "600"
+
+[files.env]
+PORT = "$FORCE_PORT"
+DATABASE_URL = "postgres://localhost/${FORCE_DB_NAME}"
+
+[[files]]
+path = "notes.txt"
+content = "Session $FORCE_FEATURE\n"
+"#
+}
+
+#[test]
+fn test_generated_files_written_on_up_and_removed_on_down() {
+    let project = create_temp_project();
+    fs::write(project.path().join(".force/config.toml"), files_config()).unwrap();
+    let capture = project.path().join("capture.txt");
+    fs::write(&capture, "").unwrap();
+    let capture = capture.canonicalize().unwrap();
+    create_script(
+        project.path(),
+        "capture",
+        &format!(
+            "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"cat .dev.local.env notes.txt > {}\"\n",
+            capture.display()
+        ),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "files-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    // Scripts see the files, so they are written before scripts run
+    let captured = fs::read_to_string(&capture).unwrap();
+    assert!(captured.contains("PORT=\"4"));
+    assert!(captured.contains("DATABASE_URL=\"postgres://localhost/"));
+    assert!(captured.contains("Session files-test"));
+
+    let worktree = project
+        .path()
+        .parent()
+        .unwrap()
+        .join("worktrees/files_test");
+    let mode = fs::metadata(worktree.join(".dev.local.env"))
+        .unwrap()
+        .permissions();
+    assert_eq!(
+        std::os::unix::fs::PermissionsExt::mode(&mode) & 0o777,
+        0o600
+    );
+
+    // An edited file is kept, an untouched one is removed
+    fs::write(worktree.join("notes.txt"), "my notes\n").unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "files-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("(modified since force up)"));
+
+    assert!(!worktree.join(".dev.local.env").exists());
+    assert_eq!(
+        fs::read_to_string(worktree.join("notes.txt")).unwrap(),
+        "my notes\n"
+    );
+    fs::remove_dir_all(&worktree).ok();
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+// Brings up a session with its worktree inside the project and returns the
+// worktree path
+fn up_local_worktree(project: &Path, feature: &str) -> std::path::PathBuf {
+    create_script(project, "hello", &script_with_down("setup"));
+    fs::write(
+        project.join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n",
+    )
+    .unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", feature])
+            .current_dir(project)
+            .output()
+            .unwrap(),
+    )
+    .success();
+    project.join(".worktrees").join(feature.replace('-', "_"))
+}
+
+#[test]
+fn test_down_refuses_uncommitted_changes() {
+    let project = create_temp_project();
+    let worktree = up_local_worktree(project.path(), "dirty-wt");
+    fs::write(worktree.join("notes.txt"), "work in progress\n").unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "dirty-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("1 uncommitted change(s)"))
+    .stderr(predicate::str::contains("--force"));
+    assert!(worktree.join("notes.txt").exists());
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "dirty-wt", "--force"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+    assert!(!worktree.exists());
+}
+
+#[test]
+fn test_down_refuses_unpushed_commits() {
+    let project = create_temp_project();
+    let worktree = up_local_worktree(project.path(), "unpushed-wt");
+    git(&worktree, &["commit", "--allow-empty", "-m", "Agent work"]);
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "unpushed-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "1 commit(s) not on any other branch",
+    ));
+
+    // Once another branch has the commit, nothing would be lost
+    git(project.path(), &["branch", "keep", "unpushed_wt"]);
+    Assert::new(
+        force_cmd()
+            .args(["down", "unpushed-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+}
+
+#[test]
+fn test_down_stash_keeps_changes() {
+    let project = create_temp_project();
+    let worktree = up_local_worktree(project.path(), "stash-wt");
+    fs::write(worktree.join("notes.txt"), "stash me\n").unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "stash-wt", "--stash"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Stashed 1 change(s)"));
+    assert!(!worktree.exists());
+
+    git(project.path(), &["stash", "pop"]);
+    assert_eq!(
+        fs::read_to_string(project.path().join("notes.txt")).unwrap(),
+        "stash me\n"
+    );
+}
+
+#[test]
+fn test_down_backup_saves_work_to_ref() {
+    let project = create_temp_project();
+    let worktree = up_local_worktree(project.path(), "backup-wt");
+    git(&worktree, &["commit", "--allow-empty", "-m", "Agent work"]);
+    fs::write(worktree.join("notes.txt"), "back me up\n").unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "backup-wt", "--backup"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("refs/force/backup/backup_wt"));
+    assert!(!worktree.exists());
+
+    let backup = "refs/force/backup/backup_wt";
+    assert_eq!(
+        git(project.path(), &["show", &format!("{}:notes.txt", backup)]),
+        "back me up"
+    );
+    assert_eq!(
+        git(
+            project.path(),
+            &["log", "-1", "--format=%s", &format!("{}^", backup)]
+        ),
+        "Agent work"
+    );
+}
+
+#[test]
+fn test_down_deletes_branch_when_configured() {
+    let project = create_temp_project();
+    up_local_worktree(project.path(), "delete-branch-wt");
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\ndelete_branch = \"always\"\n",
+    )
+    .unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "delete-branch-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Deleted branch 'delete_branch_wt'",
+    ));
+    assert_eq!(
+        git(project.path(), &["branch", "--list", "delete_branch_wt"]),
+        ""
+    );
+}
+
+#[test]
+fn test_down_keeps_unmerged_branch() {
+    let project = create_temp_project();
+    let worktree = up_local_worktree(project.path(), "unmerged-wt");
+    git(&worktree, &["commit", "--allow-empty", "-m", "Agent work"]);
+    git(project.path(), &["branch", "keep", "unmerged_wt"]);
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "unmerged-wt", "--delete-branch", "merged"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Kept branch 'unmerged_wt' (not fully merged into master)",
+    ));
+    assert_eq!(
+        git(project.path(), &["branch", "--list", "unmerged_wt"]),
+        "unmerged_wt"
+    );
+}
+
+#[test]
+fn test_down_deletes_merged_branch() {
+    let project = create_temp_project();
+    up_local_worktree(project.path(), "merged-wt");
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "merged-wt", "--delete-branch", "merged"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Deleted branch 'merged_wt'"));
+    assert_eq!(git(project.path(), &["branch", "--list", "merged_wt"]), "");
+}
+
+#[test]
+fn test_down_checks_merged_against_recorded_base() {
+    let project = create_temp_project();
+    let worktree = up_local_worktree(project.path(), "moved-head-wt");
+    git(&worktree, &["commit", "--allow-empty", "-m", "Agent work"]);
+    // The main checkout moves to a branch that has the agent's work
+    git(
+        project.path(),
+        &["checkout", "-q", "-b", "other", "moved_head_wt"],
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "moved-head-wt", "--delete-branch", "merged"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Kept branch 'moved_head_wt' (not fully merged into master)",
+    ));
+    assert_eq!(
+        git(project.path(), &["branch", "--list", "moved_head_wt"]),
+        "moved_head_wt"
+    );
+}
+
+#[test]
+fn test_down_says_branch_is_kept_with_worktree() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &script_with_down("setup"));
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\nremove_on_down = false\n",
+    )
+    .unwrap();
+    Assert::new(
+        force_cmd()
+            .args(["up", "kept-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "kept-wt", "--delete-branch", "always"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Kept branch 'kept_wt' (the worktree is kept, since remove_on_down = false)",
+    ));
+    // Still checked out in the kept worktree
+    assert_eq!(
+        git(project.path(), &["branch", "--list", "kept_wt"]),
+        "+ kept_wt"
+    );
+}
+
+#[test]
+fn test_down_stash_refuses_when_branch_is_deleted() {
+    let project = create_temp_project();
+    let worktree = up_local_worktree(project.path(), "stash-delete-wt");
+    git(&worktree, &["commit", "--allow-empty", "-m", "Agent work"]);
+
+    Assert::new(
+        force_cmd()
+            .args([
+                "down",
+                "stash-delete-wt",
+                "--stash",
+                "--delete-branch",
+                "always",
+            ])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("Use --backup instead"));
+    assert!(worktree.exists());
+}
+
+#[test]
+fn test_down_keeps_branch_force_did_not_create() {
+    let project = create_temp_project();
+    // A branch that existed before `force up`, with a commit only it has
+    git(project.path(), &["branch", "release"]);
+    git(project.path(), &["checkout", "-q", "release"]);
+    git(
+        project.path(),
+        &["commit", "--allow-empty", "-m", "Release work"],
+    );
+    git(project.path(), &["checkout", "-q", "-"]);
+    let worktree = up_local_worktree(project.path(), "release");
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "release", "--force", "--delete-branch", "always"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Kept branch 'release' (Force didn't create it)",
+    ));
+    assert!(!worktree.exists());
+    assert_eq!(
+        git(project.path(), &["branch", "--list", "release"]),
+        "release"
+    );
+}
+
+#[test]
+fn test_down_only_ignores_untouched_seeded_files() {
+    let project = create_temp_project();
+    fs::create_dir(project.path().join("config")).unwrap();
+    fs::write(project.path().join("config/app.yml"), "tracked: true\n").unwrap();
+    git(project.path(), &["add", "config/app.yml"]);
+    git(project.path(), &["commit", "-m", "Add app config"]);
+    // Untracked in the main checkout, so `copy` seeds it into the worktree
+    fs::write(project.path().join("config/local.yml"), "local: true\n").unwrap();
+
+    create_script(project.path(), "hello", &script_with_down("setup"));
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\ncopy = [\"config/*\"]\n",
+    )
+    .unwrap();
+    Assert::new(
+        force_cmd()
+            .args(["up", "seeded-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Copied config/local.yml"));
+    let worktree = project.path().join(".worktrees/seeded_wt");
+
+    // Matching the copy pattern doesn't make the agent's work Force's
+    fs::write(worktree.join("config/app.yml"), "tracked: edited\n").unwrap();
+    fs::write(worktree.join("config/new_module.rb"), "class New; end\n").unwrap();
+    Assert::new(
+        force_cmd()
+            .args(["down", "seeded-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("2 uncommitted change(s)"));
+
+    // Nor does editing a copied file
+    git(&worktree, &["checkout", "config/app.yml"]);
+    fs::remove_file(worktree.join("config/new_module.rb")).unwrap();
+    fs::write(worktree.join("config/local.yml"), "local: edited\n").unwrap();
+    Assert::new(
+        force_cmd()
+            .args(["down", "seeded-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("1 uncommitted change(s)"));
+
+    // An untouched copy is Force's own
+    fs::write(worktree.join("config/local.yml"), "local: true\n").unwrap();
+    Assert::new(
+        force_cmd()
+            .args(["down", "seeded-wt"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+    assert!(!worktree.exists());
+}
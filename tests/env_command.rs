@@ -0,0 +1,209 @@
+use assert_cmd::assert::Assert;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper to run force command
+fn force_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("force"))
+}
+
+// Helper functions - creates a git repo with initial commit for worktree support
+fn create_temp_project() -> TempDir {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+
+    // Initialize git repo
+    Command::new("git")
+        .args(["init"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to init git");
+
+    // Configure git user for commits
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git email");
+
+    Command::new("git")
+        .args(["config", "user.name", "Test User"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git name");
+
+    // Create initial commit (required for worktrees)
+    Command::new("git")
+        .args(["commit", "--allow-empty", "-m", "Initial commit"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to create initial commit");
+
+    fs::create_dir(dir.path().join(".force")).expect("Failed to create .force dir");
+    dir
+}
+
+fn create_script(project_dir: &Path, name: &str, content: &str) {
+    let script_path = project_dir.join(".force").join(format!("{}.toml", name));
+    fs::write(&script_path, content).expect("Failed to write script file");
+}
+
+// Script that records the environment it received
+fn env_capture_script(output_file: &Path) -> String {
+    format!(
+        r#"[meta]
+category = "setup"
+
+[up]
+run = "env | grep ^FORCE_ | sort > {}"
+"#,
+        output_file.display()
+    )
+}
+
+fn captured_vars(output_file: &Path) -> Vec<String> {
+    let captured = fs::read_to_string(output_file).unwrap();
+    captured.lines().map(String::from).collect()
+}
+
+#[test]
+fn test_env_matches_script_environment() {
+    let project = create_temp_project();
+    let output_file = project.path().join("env.txt");
+    fs::write(&output_file, "").unwrap();
+    let output_file = output_file.canonicalize().unwrap();
+    create_script(project.path(), "capture", &env_capture_script(&output_file));
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "env-match-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    // Load the printed variables into a shell and compare with the script's view
+    let force = assert_cmd::cargo::cargo_bin!("force");
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "eval \"$('{}' env env-match-test)\" && env | grep ^FORCE_ | sort",
+            force.display()
+        ))
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let from_env: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(String::from)
+        .collect();
+
+    assert_eq!(from_env, captured_vars(&output_file));
+}
+
+#[test]
+fn test_env_formats() {
+    let project = create_temp_project();
+    create_script(
+        project.path(),
+        "setup",
+        "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"true\"\n",
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "env-format-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    Assert::new(
+        force_cmd()
+            .args(["env", "env-format-test", "--format", "fish"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "set -gx FORCE_FEATURE 'env-format-test'",
+    ));
+
+    Assert::new(
+        force_cmd()
+            .args(["env", "env-format-test", "--format", "dotenv"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "FORCE_FEATURE_SLUG=\"env_format_test\"",
+    ));
+
+    let output = force_cmd()
+        .args(["env", "env-format-test", "--format", "json"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["FORCE_FEATURE"], "env-format-test");
+}
+
+#[test]
+fn test_env_fails_without_session() {
+    let project = create_temp_project();
+
+    Assert::new(
+        force_cmd()
+            .args(["env", "env-missing-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "No session for 'env-missing-test'",
+    ));
+}
+
+#[test]
+fn test_env_includes_custom_vars() {
+    let project = create_temp_project();
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[env]\nAPP_URL = \"http://localhost:$FORCE_PORT\"\n",
+    )
+    .unwrap();
+    create_script(
+        project.path(),
+        "setup",
+        "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"true\"\n",
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "env-custom-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let output = force_cmd()
+        .args(["env", "env-custom-test", "--format", "json"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let port = json["FORCE_PORT"].as_str().unwrap();
+    assert_eq!(json["APP_URL"], format!("http://localhost:{}", port));
+}
@@ -0,0 +1,161 @@
+use assert_cmd::assert::Assert;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper to run force command
+fn force_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("force"))
+}
+
+// Helper functions - creates a git repo with initial commit for worktree support
+fn create_temp_project() -> TempDir {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+
+    // Initialize git repo
+    Command::new("git")
+        .args(["init"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to init git");
+
+    // Configure git user for commits
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git email");
+
+    Command::new("git")
+        .args(["config", "user.name", "Test User"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git name");
+
+    // Create initial commit (required for worktrees)
+    Command::new("git")
+        .args(["commit", "--allow-empty", "-m", "Initial commit"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to create initial commit");
+
+    fs::create_dir(dir.path().join(".force")).expect("Failed to create .force dir");
+    dir
+}
+
+fn create_script(project_dir: &Path, name: &str, content: &str) {
+    let script_path = project_dir.join(".force").join(format!("{}.toml", name));
+    fs::write(&script_path, content).expect("Failed to write script file");
+}
+
+fn minimal_script() -> String {
+    "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"true\"\n".to_string()
+}
+
+fn up(project_dir: &Path, feature: &str) {
+    Assert::new(
+        force_cmd()
+            .args(["up", feature])
+            .current_dir(project_dir)
+            .output()
+            .unwrap(),
+    )
+    .success();
+}
+
+fn worktree_path(project_dir: &Path, slug: &str) -> String {
+    // Default worktree template: ../worktrees/$FORCE_FEATURE_SLUG
+    let worktree = project_dir.parent().unwrap().join("worktrees").join(slug);
+    worktree.canonicalize().unwrap().display().to_string()
+}
+
+#[test]
+fn test_exec_runs_in_session_context() {
+    let project = create_temp_project();
+    create_script(project.path(), "setup", &minimal_script());
+    up(project.path(), "exec-context-test");
+
+    let output = force_cmd()
+        .args([
+            "exec",
+            "exec-context-test",
+            "--",
+            "sh",
+            "-c",
+            "pwd; echo $FORCE_FEATURE",
+        ])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            worktree_path(project.path(), "exec_context_test").as_str(),
+            "exec-context-test"
+        ]
+    );
+}
+
+#[test]
+fn test_exec_passes_through_exit_code() {
+    let project = create_temp_project();
+    create_script(project.path(), "setup", &minimal_script());
+    up(project.path(), "exec-exit-test");
+
+    let output = force_cmd()
+        .args(["exec", "exec-exit-test", "--", "sh", "-c", "exit 7"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(7));
+}
+
+#[test]
+fn test_exec_fails_without_session() {
+    let project = create_temp_project();
+
+    Assert::new(
+        force_cmd()
+            .args(["exec", "exec-missing-test", "--", "true"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "No session for 'exec-missing-test'",
+    ));
+}
+
+#[test]
+fn test_shell_uses_shell_env_var() {
+    let project = create_temp_project();
+    create_script(project.path(), "setup", &minimal_script());
+    up(project.path(), "shell-test");
+
+    let mut child = force_cmd()
+        .args(["shell", "shell-test"])
+        .env("SHELL", "/bin/sh")
+        .current_dir(project.path())
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        use std::io::Write;
+        let mut stdin = child.stdin.take().unwrap();
+        writeln!(stdin, "echo \"feature=$FORCE_FEATURE\"; pwd").unwrap();
+    }
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("feature=shell-test"));
+    assert!(stdout.contains(&worktree_path(project.path(), "shell_test")));
+}
@@ -194,3 +194,150 @@ fn test_ls_fails_without_force_dir() {
     .failure()
     .stderr(predicate::str::contains(".force/ directory not found"));
 }
+
+#[test]
+fn test_ls_shows_distinct_ports_for_colliding_features() {
+    let project = create_temp_project();
+    create_script(project.path(), "test", &minimal_script());
+
+    // "Aa" and "BB" hash to the same port offset
+    for feature in ["Aa", "BB"] {
+        Assert::new(
+            force_cmd()
+                .args(["up", feature])
+                .current_dir(project.path())
+                .output()
+                .unwrap(),
+        )
+        .success();
+    }
+
+    let output = force_cmd()
+        .arg("ls")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let ports: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.split("port ").nth(1))
+        .collect();
+
+    assert_eq!(ports.len(), 2);
+    assert_ne!(ports[0], ports[1]);
+}
+
+#[test]
+fn test_ls_shows_named_ports() {
+    let project = create_temp_project();
+    create_script(project.path(), "test", &minimal_script());
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[ports]\nweb = 4000\napi = 5000\n",
+    )
+    .unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "ls-named-ports"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    Assert::new(
+        force_cmd()
+            .arg("ls")
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("api 5"))
+    .stdout(predicate::str::contains("web 4"));
+}
+
+#[test]
+fn test_ls_and_exec_keep_recorded_ports_after_config_change() {
+    let project = create_temp_project();
+    create_script(project.path(), "test", &minimal_script());
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n",
+    )
+    .unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "moved-base"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    // Change the base after the session was created
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n\n[ports]\nbase = 20000\n",
+    )
+    .unwrap();
+
+    let exec = force_cmd()
+        .args(["exec", "moved-base", "--", "sh", "-c", "echo $FORCE_PORT"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    let port = String::from_utf8_lossy(&exec.stdout).trim().to_string();
+    // Scripts got a port from the old base until the next `force up`
+    assert!(port.starts_with('4'), "{}", port);
+
+    Assert::new(
+        force_cmd()
+            .arg("ls")
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(format!(
+        "moved-base  port {}",
+        port
+    )));
+}
+
+#[test]
+fn test_ls_shows_failed_session() {
+    let project = create_temp_project();
+    create_script(
+        project.path(),
+        "failing",
+        r#"[meta]
+category = "setup"
+
+[up]
+run = "exit 1"
+"#,
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "ls-failed-test", "--no-rollback"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure();
+
+    Assert::new(
+        force_cmd()
+            .arg("ls")
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("ls-failed-test"))
+    .stdout(predicate::str::contains("(failed)"));
+}
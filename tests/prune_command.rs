@@ -0,0 +1,250 @@
+use assert_cmd::assert::Assert;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper to run force command
+fn force_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("force"))
+}
+
+// Helper functions - creates a git repo with initial commit for worktree support
+fn create_temp_project() -> TempDir {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+
+    // Initialize git repo
+    Command::new("git")
+        .args(["init"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to init git");
+
+    // Configure git user for commits
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git email");
+
+    Command::new("git")
+        .args(["config", "user.name", "Test User"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git name");
+
+    // Create initial commit (required for worktrees)
+    Command::new("git")
+        .args(["commit", "--allow-empty", "-m", "Initial commit"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to create initial commit");
+
+    fs::create_dir(dir.path().join(".force")).expect("Failed to create .force dir");
+    dir
+}
+
+fn create_script(project_dir: &Path, name: &str, content: &str) {
+    let script_path = project_dir.join(".force").join(format!("{}.toml", name));
+    fs::write(&script_path, content).expect("Failed to write script file");
+}
+
+fn minimal_script() -> String {
+    "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"true\"\n".to_string()
+}
+
+// Default worktree template: ../worktrees/$FORCE_FEATURE_SLUG
+fn worktree_path(project_dir: &Path, slug: &str) -> std::path::PathBuf {
+    project_dir.parent().unwrap().join("worktrees").join(slug)
+}
+
+fn run_prune(project_dir: &Path, answer: &str) -> std::process::Output {
+    let mut child = force_cmd()
+        .arg("prune")
+        .current_dir(project_dir)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        use std::io::Write;
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(answer.as_bytes()).unwrap();
+    }
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_prune_forgets_session_with_missing_worktree() {
+    let project = create_temp_project();
+    create_script(project.path(), "setup", &minimal_script());
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "prune-missing-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+    fs::remove_dir_all(worktree_path(project.path(), "prune_missing_test")).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["ls", "--check"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("prune-missing-test: worktree"))
+    .stdout(predicate::str::contains("is missing"));
+
+    Assert::new(
+        force_cmd()
+            .args(["prune", "--yes"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Forgot session 'prune-missing-test'",
+    ));
+
+    Assert::new(
+        force_cmd()
+            .args(["ls", "--check"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("No active sessions"))
+    .stdout(predicate::str::contains("No problems found"));
+}
+
+#[test]
+fn test_prune_removes_orphaned_worktree() {
+    let project = create_temp_project();
+    let marker = project.path().join("down.txt");
+    fs::write(&marker, "").unwrap();
+    let marker = marker.canonicalize().unwrap();
+    create_script(
+        project.path(),
+        "setup",
+        &format!(
+            "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"true\"\n\n[down]\nrun = \"echo $FORCE_FEATURE_SLUG >> {}\"\n",
+            marker.display()
+        ),
+    );
+
+    // A worktree left behind by a crashed `force up`
+    let worktree = worktree_path(project.path(), "prune_orphan_test");
+    let output = Command::new("git")
+        .args(["worktree", "add", "-b", "prune_orphan_test"])
+        .arg(&worktree)
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // Declining leaves it in place
+    let output = run_prune(project.path(), "n\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("has no session"));
+    assert!(stdout.contains("Skipped"));
+    assert!(worktree.exists());
+
+    let output = run_prune(project.path(), "y\n");
+    assert!(output.status.success());
+    assert!(!worktree.exists());
+    assert_eq!(fs::read_to_string(&marker).unwrap(), "prune_orphan_test\n");
+}
+
+#[test]
+fn test_prune_with_nothing_to_do() {
+    let project = create_temp_project();
+
+    Assert::new(
+        force_cmd()
+            .args(["prune"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Nothing to prune"));
+}
+
+#[test]
+fn test_prune_leaves_running_up_alone() {
+    let project = create_temp_project();
+    let marker = project.path().join("started.txt");
+    create_script(
+        project.path(),
+        "slow",
+        &format!(
+            "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"touch {} && sleep 30\"\n",
+            project
+                .path()
+                .canonicalize()
+                .unwrap()
+                .join("started.txt")
+                .display()
+        ),
+    );
+
+    let mut up = force_cmd()
+        .args(["up", "prune-running-test"])
+        .current_dir(project.path())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    for _ in 0..100 {
+        if marker.exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    assert!(marker.exists(), "force up never reached its script");
+
+    // The pending session belongs to a live `force up`
+    Assert::new(
+        force_cmd()
+            .args(["prune", "--yes"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Nothing to prune"));
+
+    // Once that run is gone, the session counts as interrupted
+    up.kill().unwrap();
+    up.wait().unwrap();
+    Assert::new(
+        force_cmd()
+            .args(["ls", "--check"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "prune-running-test: session is pending, `force up` didn't finish",
+    ));
+
+    Assert::new(
+        force_cmd()
+            .args(["prune", "--yes"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+}
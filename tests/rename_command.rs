@@ -0,0 +1,266 @@
+use assert_cmd::assert::Assert;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper to run force command
+fn force_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("force"))
+}
+
+// Helper functions - creates a git repo with initial commit for worktree support
+fn create_temp_project() -> TempDir {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+
+    // Initialize git repo
+    Command::new("git")
+        .args(["init"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to init git");
+
+    // Configure git user for commits
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git email");
+
+    Command::new("git")
+        .args(["config", "user.name", "Test User"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to configure git name");
+
+    // Create initial commit (required for worktrees)
+    Command::new("git")
+        .args(["commit", "--allow-empty", "-m", "Initial commit"])
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to create initial commit");
+
+    fs::create_dir(dir.path().join(".force")).expect("Failed to create .force dir");
+    dir
+}
+
+fn create_script(project_dir: &Path, name: &str, content: &str) {
+    let script_path = project_dir.join(".force").join(format!("{}.toml", name));
+    fs::write(&script_path, content).expect("Failed to write script file");
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn local_worktree_config(project: &Path) {
+    fs::write(
+        project.join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n",
+    )
+    .unwrap();
+}
+
+// Records the old and new values a [rename] hook sees
+fn rename_script(output_file: &Path) -> String {
+    format!(
+        r#"[meta]
+category = "database"
+
+[up]
+run = "true"
+
+[rename]
+run = "echo $FORCE_OLD_FEATURE $FORCE_FEATURE $FORCE_OLD_PORT $FORCE_PORT > {}"
+"#,
+        output_file.display()
+    )
+}
+
+fn up(project: &Path, feature: &str) {
+    Assert::new(
+        force_cmd()
+            .args(["up", feature])
+            .current_dir(project)
+            .output()
+            .unwrap(),
+    )
+    .success();
+}
+
+fn session_port(project: &Path, feature: &str) -> String {
+    let output = force_cmd()
+        .args(["exec", feature, "--", "sh", "-c", "echo $FORCE_PORT"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[test]
+fn test_rename_moves_worktree_and_branch() {
+    let project = create_temp_project();
+    local_worktree_config(project.path());
+    let output = project.path().join("rename.txt");
+    create_script(project.path(), "db", &rename_script(&output));
+    up(project.path(), "old-name");
+    let port = session_port(project.path(), "old-name");
+
+    Assert::new(
+        force_cmd()
+            .args(["rename", "old-name", "new-name"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Moved worktree to"))
+    .stdout(predicate::str::contains(
+        "Renamed branch 'old_name' to 'new_name'",
+    ))
+    .stdout(predicate::str::contains(
+        "Session 'old-name' renamed to 'new-name'.",
+    ));
+
+    let worktree = project.path().join(".worktrees/new_name");
+    assert!(worktree.is_dir());
+    assert!(!project.path().join(".worktrees/old_name").exists());
+    assert_eq!(
+        git(&worktree, &["rev-parse", "--abbrev-ref", "HEAD"]),
+        "new_name"
+    );
+    assert_eq!(
+        fs::read_to_string(&output).unwrap().trim(),
+        format!("old-name new-name {} {}", port, port)
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["ls"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("new-name"))
+    .stdout(predicate::str::contains("old-name").not());
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "new-name"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+    assert!(!worktree.exists());
+}
+
+#[test]
+fn test_rename_with_new_port() {
+    let project = create_temp_project();
+    local_worktree_config(project.path());
+    let output = project.path().join("rename.txt");
+    create_script(project.path(), "db", &rename_script(&output));
+    up(project.path(), "first");
+    let old_port = session_port(project.path(), "first");
+
+    Assert::new(
+        force_cmd()
+            .args(["rename", "first", "second", "--new-port"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let new_port = session_port(project.path(), "second");
+    assert_ne!(old_port, new_port);
+    assert_eq!(
+        fs::read_to_string(&output).unwrap().trim(),
+        format!("first second {} {}", old_port, new_port)
+    );
+}
+
+#[test]
+fn test_rename_to_existing_session_fails() {
+    let project = create_temp_project();
+    local_worktree_config(project.path());
+    create_script(
+        project.path(),
+        "db",
+        &rename_script(&project.path().join("rename.txt")),
+    );
+    up(project.path(), "one");
+    up(project.path(), "two");
+
+    Assert::new(
+        force_cmd()
+            .args(["rename", "one", "two"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("Session 'two' already exists"));
+    assert!(project.path().join(".worktrees/one").is_dir());
+}
+
+#[test]
+fn test_rename_hook_failure_keeps_session_files() {
+    let project = create_temp_project();
+    local_worktree_config(project.path());
+    create_script(
+        project.path(),
+        "db",
+        r#"[meta]
+category = "database"
+
+[up]
+run = "true"
+
+[rename]
+run = "exit 3"
+
+[[files]]
+path = "Procfile.dev"
+content = "web: serve -p $FORCE_PORT\n"
+"#,
+    );
+    up(project.path(), "before");
+
+    Assert::new(
+        force_cmd()
+            .args(["rename", "before", "after"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("stopped partway"))
+    .stderr(predicate::str::contains("moved the worktree to"))
+    .stderr(predicate::str::contains(
+        "renamed branch 'before' to 'after'",
+    ))
+    .stderr(predicate::str::contains("recorded the session as 'after'"));
+
+    // The session still knows its generated file, so down cleans it up
+    let worktree = project.path().join(".worktrees/after");
+    assert!(worktree.join("Procfile.dev").exists());
+    Assert::new(
+        force_cmd()
+            .args(["down", "after"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Removed"));
+    assert!(!worktree.exists());
+}
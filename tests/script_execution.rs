@@ -497,3 +497,575 @@ run = "echo \"WORKTREE=$FORCE_WORKTREE\" > {} && echo \"PWD=$(pwd)\" >> {}"
         pwd_val
     );
 }
+
+fn dependent_script(category: &str, depends_on: &[&str], name: &str, output_file: &Path) -> String {
+    let abs_path = output_file
+        .canonicalize()
+        .unwrap_or_else(|_| output_file.to_path_buf());
+    let deps = depends_on
+        .iter()
+        .map(|d| format!("\"{}\"", d))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"[meta]
+category = "{}"
+depends_on = [{}]
+
+[up]
+run = "echo '{}' >> {}"
+"#,
+        category,
+        deps,
+        name,
+        abs_path.display()
+    )
+}
+
+#[test]
+fn test_depends_on_overrides_category_order() {
+    let project = create_temp_project();
+    let output_file = project.path().join("order.txt");
+    fs::write(&output_file, "").unwrap();
+
+    // "db" sorts before "setup", but migrate needs the database first
+    create_script(
+        project.path(),
+        "migrate",
+        &dependent_script("db", &["database"], "migrate", &output_file),
+    );
+    create_script(
+        project.path(),
+        "database",
+        &dependent_script("setup", &[], "database", &output_file),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "feature"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let output = fs::read_to_string(&output_file).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines, vec!["database", "migrate"]);
+}
+
+#[test]
+fn test_missing_dependency_fails() {
+    let project = create_temp_project();
+    let output_file = project.path().join("order.txt");
+    fs::write(&output_file, "").unwrap();
+
+    create_script(
+        project.path(),
+        "migrate",
+        &dependent_script("setup", &["database"], "migrate", &output_file),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "feature"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicates::str::contains(
+        "migrate.toml depends on 'database'",
+    ));
+}
+
+#[test]
+fn test_dependency_cycle_fails() {
+    let project = create_temp_project();
+    let output_file = project.path().join("order.txt");
+    fs::write(&output_file, "").unwrap();
+
+    create_script(
+        project.path(),
+        "alpha",
+        &dependent_script("setup", &["beta"], "alpha", &output_file),
+    );
+    create_script(
+        project.path(),
+        "beta",
+        &dependent_script("setup", &["alpha"], "beta", &output_file),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "feature"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicates::str::contains(
+        "Dependency cycle between scripts: alpha.toml -> beta.toml -> alpha.toml",
+    ));
+
+    // Nothing should have run
+    assert_eq!(fs::read_to_string(&output_file).unwrap(), "");
+}
+
+// Script that marks itself started, then waits for its partner to start too.
+// Two of these only both succeed when they run at the same time.
+fn rendezvous_script(category: &str, name: &str, partner: &str, dir: &Path) -> String {
+    let dir = dir.canonicalize().unwrap();
+    format!(
+        r#"[meta]
+category = "{}"
+
+[up]
+run = """
+echo 'hello from {}'
+touch {}/{}.started
+for i in $(seq 50); do
+  [ -f {}/{}.started ] && exit 0
+  sleep 0.1
+done
+exit 1
+"""
+"#,
+        category,
+        name,
+        dir.display(),
+        name,
+        dir.display(),
+        partner
+    )
+}
+
+#[test]
+fn test_parallel_category_runs_scripts_concurrently() {
+    let project = create_temp_project();
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[categories.deps]\nparallel = true\n",
+    )
+    .unwrap();
+    create_script(
+        project.path(),
+        "npm",
+        &rendezvous_script("deps", "npm", "bundle", project.path()),
+    );
+    create_script(
+        project.path(),
+        "bundle",
+        &rendezvous_script("deps", "bundle", "npm", project.path()),
+    );
+
+    let output = force_cmd()
+        .args(["up", "parallel-category-test"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+
+    Assert::new(output).success();
+    assert!(stdout.contains("[deps/npm] hello from npm"));
+    assert!(stdout.contains("[deps/bundle] hello from bundle"));
+}
+
+#[test]
+fn test_jobs_flag_runs_scripts_concurrently() {
+    let project = create_temp_project();
+    create_script(
+        project.path(),
+        "npm",
+        &rendezvous_script("deps", "npm", "bundle", project.path()),
+    );
+    create_script(
+        project.path(),
+        "bundle",
+        &rendezvous_script("deps", "bundle", "npm", project.path()),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "jobs-flag-test", "--jobs", "2"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+}
+
+#[test]
+fn test_jobs_flag_keeps_priority_order() {
+    let project = create_temp_project();
+    let marker = project.path().join("first.done");
+    let marker_abs = project.path().canonicalize().unwrap().join("first.done");
+    create_script(
+        project.path(),
+        "first",
+        &format!(
+            "[meta]\ncategory = \"deps\"\npriority = 1\n\n[up]\nrun = \"sleep 0.3 && touch {}\"\n",
+            marker_abs.display()
+        ),
+    );
+    create_script(
+        project.path(),
+        "second",
+        &format!(
+            "[meta]\ncategory = \"deps\"\npriority = 2\n\n[up]\nrun = \"test -f {}\"\n",
+            marker_abs.display()
+        ),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "jobs-priority-test", "--jobs", "2"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+    assert!(marker.exists());
+}
+
+#[test]
+fn test_jobs_one_runs_parallel_category_in_order() {
+    let project = create_temp_project();
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[categories.deps]\nparallel = true\n",
+    )
+    .unwrap();
+    create_script(
+        project.path(),
+        "npm",
+        &rendezvous_script("deps", "npm", "bundle", project.path()),
+    );
+    create_script(
+        project.path(),
+        "bundle",
+        &rendezvous_script("deps", "bundle", "npm", project.path()),
+    );
+
+    // Run one at a time, the first script can never see its partner start
+    Assert::new(
+        force_cmd()
+            .args(["up", "jobs-one-test", "--jobs", "1"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure();
+}
+
+#[test]
+fn test_parallel_failure_stops_new_scripts() {
+    let project = create_temp_project();
+    let marker = project.path().join("late.txt");
+    fs::write(&marker, "").unwrap();
+    let marker = marker.canonicalize().unwrap();
+
+    create_script(
+        project.path(),
+        "alpha",
+        "[meta]\ncategory = \"deps\"\n\n[up]\nrun = \"exit 1\"\n",
+    );
+    create_script(
+        project.path(),
+        "bravo",
+        "[meta]\ncategory = \"deps\"\n\n[up]\nrun = \"sleep 0.5\"\n",
+    );
+    create_script(
+        project.path(),
+        "charlie",
+        &format!(
+            "[meta]\ncategory = \"deps\"\n\n[up]\nrun = \"echo started > {}\"\n",
+            marker.display()
+        ),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "parallel-failure-test", "--jobs", "2"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicates::str::contains("Script 'alpha' failed"));
+
+    assert_eq!(fs::read_to_string(&marker).unwrap(), "");
+}
+
+#[test]
+fn test_ready_check_waits_before_later_scripts() {
+    let project = create_temp_project();
+    let seen_file = project.path().join("seen.txt");
+    fs::write(&seen_file, "").unwrap();
+    let seen_file = seen_file.canonicalize().unwrap();
+    let ready_file = seen_file.with_file_name("ready.txt");
+
+    create_script(
+        project.path(),
+        "server",
+        &format!(
+            r#"[meta]
+category = "services"
+
+[up]
+run = "sleep 0.5; touch {ready}; exec sleep 1000"
+service = true
+
+[up.ready]
+command = "test -f {ready}"
+interval = "100ms"
+timeout = "10s"
+"#,
+            ready = ready_file.display()
+        ),
+    );
+    create_script(
+        project.path(),
+        "seed",
+        &format!(
+            "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"test -f {} && echo ready > {}\"\n",
+            ready_file.display(),
+            seen_file.display()
+        ),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "ready-wait-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicates::str::contains("[services/server] ready"));
+
+    assert_eq!(fs::read_to_string(&seen_file).unwrap().trim(), "ready");
+
+    force_cmd()
+        .args(["down", "ready-wait-test"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+}
+
+#[test]
+fn test_ready_check_timeout_fails_up() {
+    let project = create_temp_project();
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    create_script(
+        project.path(),
+        "server",
+        &format!(
+            r#"[meta]
+category = "services"
+
+[up]
+run = "exec sleep 1000"
+service = true
+
+[up.ready]
+tcp = "127.0.0.1:{}"
+interval = "100ms"
+timeout = "500ms"
+"#,
+            port
+        ),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "ready-timeout-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicates::str::contains(
+        "Script 'server' never became ready",
+    ))
+    .stderr(predicates::str::contains("still failing after 500ms"));
+}
+
+#[test]
+fn test_retries_failed_script() {
+    let project = create_temp_project();
+    let counter = project.path().join("attempts.txt");
+    fs::write(&counter, "").unwrap();
+    let counter = counter.canonicalize().unwrap();
+
+    // Fails on the first attempt, succeeds on the second
+    create_script(
+        project.path(),
+        "flaky",
+        &format!(
+            r#"[meta]
+category = "deps"
+
+[up]
+run = "echo attempt >> {path}; test $(wc -l < {path}) -ge 2"
+retries = 2
+retry_delay = "100ms"
+"#,
+            path = counter.display()
+        ),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "retry-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicates::str::contains(
+        "Script 'flaky' failed with exit code 1 on attempt 1 of 3, retrying in 100ms",
+    ));
+
+    assert_eq!(fs::read_to_string(&counter).unwrap().lines().count(), 2);
+}
+
+#[test]
+fn test_reports_last_failed_attempt() {
+    let project = create_temp_project();
+
+    create_script(
+        project.path(),
+        "broken",
+        "[meta]\ncategory = \"deps\"\n\n[up]\nrun = \"exit 3\"\nretries = 1\n",
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "retry-exhausted-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicates::str::contains(
+        "Script 'broken' failed with exit code 3 on attempt 2 of 2",
+    ));
+}
+
+#[test]
+fn test_timeout_kills_script_process_tree() {
+    let project = create_temp_project();
+    let marker = project.path().join("survived.txt");
+    fs::write(&marker, "").unwrap();
+    let marker = marker.canonicalize().unwrap();
+
+    // The background subshell would write the marker if it outlived the timeout
+    create_script(
+        project.path(),
+        "hung",
+        &format!(
+            r#"[meta]
+category = "db"
+
+[up]
+run = "(sleep 2; echo survived > {}) & sleep 1000"
+timeout = "300ms"
+"#,
+            marker.display()
+        ),
+    );
+
+    let started = std::time::Instant::now();
+    Assert::new(
+        force_cmd()
+            .args(["up", "timeout-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicates::str::contains(
+        "Script 'hung' timed out after 300ms and was killed (exit code 143)",
+    ));
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+
+    std::thread::sleep(std::time::Duration::from_millis(2500));
+    assert_eq!(fs::read_to_string(&marker).unwrap(), "");
+}
+
+#[test]
+fn test_custom_env_vars_from_config_and_script() {
+    let project = create_temp_project();
+    let output_file = project.path().join("custom_env.txt");
+    fs::write(&output_file, "").unwrap();
+    let output_file = output_file.canonicalize().unwrap();
+
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[env]\nDATABASE_URL = \"postgres://localhost/${FORCE_DB_NAME}\"\nRAILS_ENV = \"development\"\n",
+    )
+    .unwrap();
+    create_script(
+        project.path(),
+        "capture",
+        &format!(
+            r#"[meta]
+category = "setup"
+
+[up]
+run = "echo \"$DATABASE_URL\" > {path}; echo \"postgres://localhost/$FORCE_DB_NAME\" >> {path}; echo $RAILS_ENV >> {path}"
+
+[up.env]
+RAILS_ENV = "test"
+"#,
+            path = output_file.display()
+        ),
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "custom-env-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let output = fs::read_to_string(&output_file).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], lines[1], "DATABASE_URL should use FORCE_DB_NAME");
+    assert_eq!(lines[2], "test", "[up.env] should override [env]");
+}
+
+#[test]
+fn test_unknown_env_reference_fails_at_load() {
+    let project = create_temp_project();
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[env]\nDATABASE_URL = \"postgres://localhost/$FORCE_DB\"\n",
+    )
+    .unwrap();
+    create_script(
+        project.path(),
+        "setup",
+        "[meta]\ncategory = \"setup\"\n\n[up]\nrun = \"true\"\n",
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "unknown-env-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicates::str::contains(
+        "[env] in config.toml: Unknown variable 'FORCE_DB'",
+    ));
+}
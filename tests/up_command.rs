@@ -342,3 +342,905 @@ path = ".worktrees/$FORCE_FEATURE_SLUG"
         worktree_path
     );
 }
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[test]
+fn test_up_uses_branch_template_and_base() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+
+    // "stable" points at the first commit, HEAD moves on past it
+    git(project.path(), &["branch", "stable"]);
+    git(
+        project.path(),
+        &["commit", "--allow-empty", "-m", "Second commit"],
+    );
+    let stable = git(project.path(), &["rev-parse", "stable"]);
+
+    let config = r#"[worktree]
+path = ".worktrees/$FORCE_FEATURE_SLUG"
+branch = "agent/$FORCE_FEATURE_SLUG"
+base = "stable"
+"#;
+    fs::write(project.path().join(".force/config.toml"), config).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "main"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let worktree_path = project.path().join(".worktrees/main");
+    assert_eq!(
+        git(&worktree_path, &["rev-parse", "--abbrev-ref", "HEAD"]),
+        "agent/main"
+    );
+    assert_eq!(git(&worktree_path, &["rev-parse", "HEAD"]), stable);
+}
+
+#[test]
+fn test_up_base_flag_overrides_config() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+
+    let first = git(project.path(), &["rev-parse", "HEAD"]);
+    git(
+        project.path(),
+        &["commit", "--allow-empty", "-m", "Second commit"],
+    );
+
+    let config = r#"[worktree]
+path = ".worktrees/$FORCE_FEATURE_SLUG"
+base = "HEAD"
+"#;
+    fs::write(project.path().join(".force/config.toml"), config).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "base-flag", "--base", &first])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let worktree_path = project.path().join(".worktrees/base_flag");
+    assert_eq!(git(&worktree_path, &["rev-parse", "HEAD"]), first);
+}
+
+#[test]
+fn test_up_fails_on_unknown_base() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+
+    let config = r#"[worktree]
+path = ".worktrees/$FORCE_FEATURE_SLUG"
+"#;
+    fs::write(project.path().join(".force/config.toml"), config).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "unknown-base", "--base", "origin/nope"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("Base 'origin/nope'"));
+
+    assert!(!project.path().join(".worktrees/unknown_base").exists());
+}
+
+#[test]
+fn test_up_rejects_branch_template_without_slug() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\nbranch = \"agent/work\"\n",
+    )
+    .unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "no-slug-branch"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("must include $FORCE_FEATURE_SLUG"));
+}
+
+// Mirrors env::hash_to_offset so tests can predict a feature's preferred port
+fn preferred_port(feature: &str) -> u16 {
+    let hash: u32 = feature
+        .bytes()
+        .fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32));
+    4000 + (hash % 1000) as u16
+}
+
+#[test]
+fn test_up_reassigns_port_when_bound() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+
+    let port = preferred_port("port-bound-test");
+    let _listener = std::net::TcpListener::bind(("127.0.0.1", port)).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "port-bound-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(format!("Port {} is taken", port)));
+}
+
+#[test]
+fn test_up_keeps_port_for_existing_session() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+
+    let port = preferred_port("port-keep-test");
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "port-keep-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(format!("Port: {}", port)));
+
+    // A running server now holds the port, but the session keeps it
+    let _listener = std::net::TcpListener::bind(("127.0.0.1", port)).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "port-keep-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains(format!("Port: {}", port)));
+}
+
+#[test]
+fn test_up_exports_named_ports() {
+    let project = create_temp_project();
+    let output_file = project.path().join("ports_output.txt");
+    fs::write(&output_file, "").unwrap();
+    let abs_path = output_file.canonicalize().unwrap();
+
+    let config = r#"[ports]
+web = 4000
+api = 5000
+"#;
+    fs::write(project.path().join(".force/config.toml"), config).unwrap();
+
+    let script = format!(
+        r#"[meta]
+category = "setup"
+
+[up]
+run = "echo \"$FORCE_PORT $FORCE_PORT_WEB $FORCE_PORT_API $FORCE_PORT_OFFSET\" >> {}"
+"#,
+        abs_path.display()
+    );
+    create_script(project.path(), "ports", &script);
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "named-ports-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let output = fs::read_to_string(&output_file).unwrap();
+    let values: Vec<u16> = output
+        .split_whitespace()
+        .map(|v| v.parse().unwrap())
+        .collect();
+    let offset = values[3];
+    assert_eq!(values[0], 4000 + offset);
+    assert_eq!(values[1], 4000 + offset);
+    assert_eq!(values[2], 5000 + offset);
+}
+
+#[test]
+fn test_up_fails_on_reserved_port_name() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[ports]\noffset = 4000\n",
+    )
+    .unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "reserved-port-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("reserved"));
+}
+
+#[test]
+fn test_up_uses_configured_port_base_and_range() {
+    let project = create_temp_project();
+    let output_file = project.path().join("port_output.txt");
+    fs::write(&output_file, "").unwrap();
+    let abs_path = output_file.canonicalize().unwrap();
+
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[ports]\nbase = 31000\nrange = 50\n",
+    )
+    .unwrap();
+    let script = format!(
+        r#"[meta]
+category = "setup"
+
+[up]
+run = "echo $FORCE_PORT >> {}"
+"#,
+        abs_path.display()
+    );
+    create_script(project.path(), "port", &script);
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "port-range-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let port: u16 = fs::read_to_string(&output_file)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert!((31000..31050).contains(&port), "port {} out of range", port);
+}
+
+#[test]
+fn test_up_fails_when_port_range_overflows() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[ports]\nbase = 65000\nrange = 1000\n",
+    )
+    .unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "port-overflow-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("must stay under 65535"));
+}
+
+#[test]
+fn test_up_rolls_back_completed_scripts_on_failure() {
+    let project = create_temp_project();
+    let output_file = project.path().join("rollback.txt");
+    fs::write(&output_file, "").unwrap();
+    let abs_path = output_file.canonicalize().unwrap();
+
+    for (name, priority) in [("first", 1), ("second", 2)] {
+        let script = format!(
+            r#"[meta]
+category = "setup"
+priority = {}
+
+[up]
+run = "echo 'up {}' >> {}"
+
+[down]
+run = "echo 'down {}' >> {}"
+"#,
+            priority,
+            name,
+            abs_path.display(),
+            name,
+            abs_path.display()
+        );
+        create_script(project.path(), name, &script);
+    }
+    let failing = r#"[meta]
+category = "setup"
+priority = 3
+
+[up]
+run = "exit 1"
+
+[down]
+run = "echo 'should not run' >> /dev/null"
+"#;
+    create_script(project.path(), "third", failing);
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "rollback-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stdout(predicate::str::contains("Rollback summary"))
+    .stdout(predicate::str::contains("rolled back  setup/first"))
+    .stdout(predicate::str::contains("removed      worktree"))
+    .stdout(predicate::str::contains(
+        "deleted      branch rollback_test",
+    ));
+    assert!(
+        git(
+            project.path(),
+            &["rev-parse", "--verify", "--quiet", "rollback_test"]
+        )
+        .is_empty(),
+        "Branch created by the failed run should be deleted"
+    );
+
+    let output = fs::read_to_string(&output_file).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines,
+        vec!["up first", "up second", "down second", "down first"]
+    );
+
+    let worktree_path = project
+        .path()
+        .parent()
+        .unwrap()
+        .join("worktrees/rollback_test");
+    assert!(
+        !worktree_path.exists(),
+        "Worktree should be removed on rollback"
+    );
+
+    Assert::new(
+        force_cmd()
+            .arg("ls")
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("No active sessions"));
+}
+
+#[test]
+fn test_up_no_rollback_leaves_worktree() {
+    let project = create_temp_project();
+    create_script(project.path(), "failing", &failing_script("setup"));
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "no-rollback-test", "--no-rollback"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stdout(predicate::str::contains("--no-rollback"));
+
+    let worktree_path = project
+        .path()
+        .parent()
+        .unwrap()
+        .join("worktrees/no_rollback_test");
+    assert!(
+        worktree_path.exists(),
+        "Worktree should be kept with --no-rollback"
+    );
+}
+
+#[test]
+fn test_up_rejects_service_in_down() {
+    let project = create_temp_project();
+    create_script(
+        project.path(),
+        "server",
+        r#"[meta]
+category = "services"
+
+[up]
+run = "sleep 1000"
+service = true
+
+[down]
+run = "true"
+service = true
+"#,
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "service-down-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("only supported in [up]"));
+}
+
+#[test]
+fn test_up_copies_and_links_ignored_files() {
+    let project = create_temp_project();
+    create_script(
+        project.path(),
+        "check",
+        r#"[meta]
+category = "setup"
+
+[up]
+run = "test -f .env && test -f node_modules/pkg/index.js"
+"#,
+    );
+    fs::write(project.path().join(".env"), "SECRET=1\n").unwrap();
+    fs::create_dir_all(project.path().join("node_modules/pkg")).unwrap();
+    fs::write(project.path().join("node_modules/pkg/index.js"), "").unwrap();
+
+    let config = r#"[worktree]
+path = ".worktrees/$FORCE_FEATURE_SLUG"
+copy = [".env"]
+link = ["node_modules"]
+"#;
+    fs::write(project.path().join(".force/config.toml"), config).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "seeded"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Copied .env"))
+    .stdout(predicate::str::contains("Linked node_modules"));
+
+    let worktree_path = project.path().join(".worktrees/seeded");
+    assert!(!worktree_path.join(".env").is_symlink());
+    assert!(worktree_path.join("node_modules").is_symlink());
+
+    // Seeded files aren't unsaved work, so down needs no --force
+    Assert::new(
+        force_cmd()
+            .args(["down", "seeded"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+    assert!(project.path().join("node_modules/pkg/index.js").exists());
+}
+
+#[test]
+fn test_up_initializes_submodules() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+
+    // A local repository to use as the submodule
+    let library = create_temp_project();
+    fs::write(library.path().join("lib.txt"), "library\n").unwrap();
+    git(library.path(), &["add", "lib.txt"]);
+    git(library.path(), &["commit", "-m", "Add lib"]);
+
+    // Git refuses local file:// submodules unless allowed explicitly
+    let library_path = library.path().to_string_lossy().to_string();
+    git(
+        project.path(),
+        &[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "--quiet",
+            &library_path,
+            "vendor/lib",
+        ],
+    );
+    git(project.path(), &["commit", "-m", "Add submodule"]);
+
+    let config = r#"[worktree]
+path = ".worktrees/$FORCE_FEATURE_SLUG"
+submodules = true
+"#;
+    fs::write(project.path().join(".force/config.toml"), config).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "with-submodules"])
+            .env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "protocol.file.allow")
+            .env("GIT_CONFIG_VALUE_0", "always")
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("git submodule update --init"));
+
+    let worktree_path = project.path().join(".worktrees/with_submodules");
+    assert_eq!(
+        fs::read_to_string(worktree_path.join("vendor/lib/lib.txt")).unwrap(),
+        "library\n"
+    );
+}
+
+#[test]
+fn test_up_removes_worktree_when_checkout_setup_fails() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+
+    // Submodule update fails on a submodule whose URL doesn't exist
+    fs::write(
+        project.path().join(".gitmodules"),
+        "[submodule \"missing\"]\n\tpath = missing\n\turl = /nonexistent/repo\n",
+    )
+    .unwrap();
+    let head = git(project.path(), &["rev-parse", "HEAD"]);
+    git(
+        project.path(),
+        &[
+            "update-index",
+            "--add",
+            "--cacheinfo",
+            &format!("160000,{},missing", head),
+        ],
+    );
+    git(project.path(), &["add", ".gitmodules"]);
+    git(project.path(), &["commit", "-m", "Add broken submodule"]);
+
+    let config = r#"[worktree]
+path = ".worktrees/$FORCE_FEATURE_SLUG"
+submodules = true
+"#;
+    fs::write(project.path().join(".force/config.toml"), config).unwrap();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "broken-submodule"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains("Failed to initialize submodules"));
+
+    assert!(!project.path().join(".worktrees/broken_submodule").exists());
+    assert_eq!(
+        git(project.path(), &["branch", "--list", "broken_submodule"]),
+        ""
+    );
+}
+
+#[test]
+fn test_up_from_remote_branch_tracks_it() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n",
+    )
+    .unwrap();
+
+    // Publish a colleague's branch to a remote, fetch it, then remove the
+    // remote so Force has to work from the fetched ref alone
+    let remote = TempDir::new().unwrap();
+    git(remote.path(), &["init", "--bare", "--quiet"]);
+    git(
+        project.path(),
+        &["remote", "add", "origin", &remote.path().to_string_lossy()],
+    );
+    git(project.path(), &["checkout", "--quiet", "-b", "colleague"]);
+    git(
+        project.path(),
+        &["commit", "--allow-empty", "-m", "Colleague work"],
+    );
+    git(project.path(), &["push", "--quiet", "origin", "colleague"]);
+    let colleague = git(project.path(), &["rev-parse", "HEAD"]);
+    git(project.path(), &["checkout", "--quiet", "-"]);
+    git(project.path(), &["branch", "-D", "colleague"]);
+    git(project.path(), &["fetch", "--quiet", "origin"]);
+    drop(remote);
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "review", "--from", "origin/colleague"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    let worktree_path = project.path().join(".worktrees/review");
+    assert_eq!(git(&worktree_path, &["rev-parse", "HEAD"]), colleague);
+    assert_eq!(
+        git(
+            &worktree_path,
+            &["rev-parse", "--abbrev-ref", "@{upstream}"]
+        ),
+        "origin/colleague"
+    );
+
+    // Running it again is fine while the branch is still there
+    Assert::new(
+        force_cmd()
+            .args(["up", "review", "--from", "origin/colleague"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    // A session whose branch has moved on isn't at the remote branch anymore
+    git(
+        &worktree_path,
+        &["commit", "--allow-empty", "-m", "Review notes"],
+    );
+    Assert::new(
+        force_cmd()
+            .args(["up", "review", "--from", "origin/colleague"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "Branch 'review' already exists and isn't at --from origin/colleague",
+    ));
+
+    // Nor is an unrelated local branch that happens to have the name
+    git(project.path(), &["branch", "stale"]);
+    Assert::new(
+        force_cmd()
+            .args(["up", "stale", "--from", "origin/colleague"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "Branch 'stale' already exists and isn't at --from origin/colleague",
+    ));
+    assert!(!project.path().join(".worktrees/stale").exists());
+}
+
+#[test]
+fn test_up_from_fetched_pull_request_ref() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n",
+    )
+    .unwrap();
+
+    let first = git(project.path(), &["rev-parse", "HEAD"]);
+    git(project.path(), &["update-ref", "refs/pull/12/head", &first]);
+    git(
+        project.path(),
+        &["commit", "--allow-empty", "-m", "Second commit"],
+    );
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "pr-12", "--from-ref", "refs/pull/12/head"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+    let worktree_path = project.path().join(".worktrees/pr_12");
+    assert_eq!(git(&worktree_path, &["rev-parse", "HEAD"]), first);
+}
+
+#[test]
+fn test_up_from_missing_ref_fails() {
+    let project = create_temp_project();
+    create_script(project.path(), "hello", &minimal_script("setup"));
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "missing-remote", "--from", "origin/nope"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "Remote branch 'origin/nope' not found locally",
+    ))
+    .stderr(predicate::str::contains("git fetch origin"));
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "missing-pr", "--from-ref", "refs/pull/99/head"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "Ref 'refs/pull/99/head' not found locally",
+    ));
+}
+
+#[test]
+fn test_up_rejects_colliding_slug() {
+    let project = create_temp_project();
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n",
+    )
+    .unwrap();
+    create_script(project.path(), "setup", &minimal_script("setup"));
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "add-login"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success();
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "Add Login"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "'Add Login' has the slug 'add_login', which session 'add-login' already uses",
+    ));
+
+    // Other commands find the session by its slug
+    Assert::new(
+        force_cmd()
+            .args(["exec", "add_login", "--", "sh", "-c", "echo $FORCE_FEATURE"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("add-login"));
+
+    Assert::new(
+        force_cmd()
+            .args(["down", "add_login"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("Session 'add-login' torn down."));
+}
+
+#[test]
+fn test_up_transliterates_feature_name() {
+    let project = create_temp_project();
+    fs::write(
+        project.path().join(".force/config.toml"),
+        "[worktree]\npath = \".worktrees/$FORCE_FEATURE_SLUG\"\n",
+    )
+    .unwrap();
+    create_script(project.path(), "setup", &minimal_script("setup"));
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "café-menü"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("slug: cafe_menu"));
+    assert!(project.path().join(".worktrees/cafe_menu").is_dir());
+}
+
+#[test]
+fn test_up_rejects_name_without_slug() {
+    let project = create_temp_project();
+    create_script(project.path(), "setup", &minimal_script("setup"));
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "日本語"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "Feature name '日本語' has no letters or digits to build a slug from",
+    ));
+}
+
+#[test]
+fn test_up_rolls_back_when_writing_files_fails() {
+    let project = create_temp_project();
+    // .git is a file in a worktree, so nothing can be written beneath it
+    let script = r#"[meta]
+category = "setup"
+
+[[files]]
+path = ".git/nested/file"
+content = "x"
+
+[up]
+run = "true"
+"#;
+    create_script(project.path(), "broken", script);
+
+    Assert::new(
+        force_cmd()
+            .args(["up", "files-fail-test"])
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .failure()
+    .stdout(predicate::str::contains("removed      worktree"))
+    .stdout(predicate::str::contains(
+        "deleted      branch files_fail_test",
+    ));
+    assert!(
+        !project
+            .path()
+            .parent()
+            .unwrap()
+            .join("worktrees/files_fail_test")
+            .exists(),
+        "Worktree should be removed on rollback"
+    );
+    assert!(
+        git(
+            project.path(),
+            &["rev-parse", "--verify", "--quiet", "files_fail_test"]
+        )
+        .is_empty()
+    );
+
+    Assert::new(
+        force_cmd()
+            .arg("ls")
+            .current_dir(project.path())
+            .output()
+            .unwrap(),
+    )
+    .success()
+    .stdout(predicate::str::contains("No active sessions"));
+}